@@ -4,8 +4,8 @@
 use crate::accept_type::AcceptType;
 use crate::response::{
     bcs_api_disabled, block_not_found_by_height, block_not_found_by_version,
-    block_pruned_by_height, json_api_disabled, version_not_found, version_pruned, ForbiddenError,
-    InternalError, ServiceUnavailableError, StdApiError,
+    block_pruned_by_height, json_api_disabled, ledger_version_too_high, version_pruned,
+    ForbiddenError, InternalError, ServiceUnavailableError, StdApiError,
 };
 use anyhow::{ensure, format_err, Context as AnyhowContext, Result};
 use aptos_api_types::{AptosErrorCode, AsConverter, BcsBlock, LedgerInfo, TransactionOnChainData};
@@ -164,7 +164,7 @@ impl Context {
 
         // This is too far in the future, a retriable case
         if requested_ledger_version > latest_ledger_info.version() {
-            return Err(version_not_found(
+            return Err(ledger_version_too_high(
                 requested_ledger_version,
                 &latest_ledger_info,
             ));
@@ -265,7 +265,7 @@ impl Context {
         if version < latest_ledger_info.oldest_ledger_version.0 {
             return Err(version_pruned(version, latest_ledger_info));
         } else if version > latest_ledger_info.version() {
-            return Err(version_not_found(version, latest_ledger_info));
+            return Err(ledger_version_too_high(version, latest_ledger_info));
         }
 
         let (first_version, last_version, new_block_event) = self
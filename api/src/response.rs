@@ -512,11 +512,16 @@ pub fn api_disabled<S: Display, E: ForbiddenError>(identifier: S) -> E {
     )
 }
 
-pub fn version_not_found<E: NotFoundError>(ledger_version: u64, ledger_info: &LedgerInfo) -> E {
-    build_not_found(
-        "Ledger version",
-        format!("Ledger version({})", ledger_version),
-        AptosErrorCode::VersionNotFound,
+pub fn ledger_version_too_high<E: NotFoundError>(
+    ledger_version: u64,
+    ledger_info: &LedgerInfo,
+) -> E {
+    E::not_found_with_code(
+        &format!(
+            "Ledger version({}) is ahead of the latest ledger version",
+            ledger_version
+        ),
+        AptosErrorCode::LedgerVersionTooHigh,
         ledger_info,
     )
 }
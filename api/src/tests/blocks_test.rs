@@ -23,6 +23,21 @@ async fn test_get_unknown_block_by_height() {
     context.check_golden_output(resp);
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_block_by_version_ahead_of_ledger() {
+    let mut context = new_test_context(current_function_name!());
+
+    let resp = context
+        .expect_status_code(404)
+        .get(&blocks_by_version(1000))
+        .await;
+    context.check_golden_output(resp);
+}
+
 fn blocks_by_height(height: u64) -> String {
     format!("/blocks/by_height/{}", height)
 }
+
+fn blocks_by_version(version: u64) -> String {
+    format!("/blocks/by_version/{}", version)
+}
@@ -80,6 +80,10 @@ pub enum AptosErrorCode {
     /// Usually means the block is fully or partially pruned or the height / version is ahead
     /// of the latest version
     BlockNotFound = 108,
+    /// Ledger version is ahead of the latest committed version
+    ///
+    /// Unlike [`AptosErrorCode::VersionNotFound`], the version may become available later
+    LedgerVersionTooHigh = 109,
 
     /// Ledger version is pruned
     VersionPruned = 200,
@@ -100,6 +104,8 @@ pub enum AptosErrorCode {
     HealthCheckFailed = 500,
     /// The mempool is full, no new transactions can be submitted.
     MempoolIsFull = 501,
+    /// The chain is changing epochs, so data may be temporarily incomplete
+    EpochChangeInProgress = 502,
 
     /// Internal server error
     InternalError = 600,
@@ -520,6 +520,16 @@ module aptos_framework::account {
         coin::register<AptosCoin>(&resource_account);
     }
 
+    #[test(user = @0x1)]
+    public entry fun test_create_resource_account_address(user: signer) {
+        // The address is sha3_256(source || seed), which Rosetta also derives offline
+        let (resource_account, _) = create_resource_account(&user, x"01");
+        assert!(
+            signer::address_of(&resource_account) == @0x4403707de4f05dadc8266c6431921838590601b1df6a448601d518beb13e97fd,
+            0
+        );
+    }
+
     #[test_only]
     struct DummyResource has key { }
 
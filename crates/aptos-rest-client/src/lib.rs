@@ -831,6 +831,32 @@ impl Client {
         self.json(response).await
     }
 
+    /// Retrieves a table item by the table's handle as formatted by the API, at the latest
+    /// version if no version is given
+    pub async fn get_table_item_at_version<K: Serialize>(
+        &self,
+        table_handle: &str,
+        key_type: &str,
+        value_type: &str,
+        key: K,
+        version: Option<u64>,
+    ) -> AptosResult<Response<Value>> {
+        let url = self.build_path(&format!("tables/{}/item", table_handle))?;
+        let data = json!({
+            "key_type": key_type,
+            "value_type": value_type,
+            "key": json!(key),
+        });
+
+        let mut request = self.inner.post(url).json(&data);
+        if let Some(version) = version {
+            request = request.query(&[("ledger_version", version)]);
+        }
+
+        let response = request.send().await?;
+        self.json(response).await
+    }
+
     pub async fn get_account(&self, address: AccountAddress) -> AptosResult<Response<Account>> {
         let url = self.build_path(&format!("accounts/{}", address))?;
         let response = self.inner.get(url).send().await?;
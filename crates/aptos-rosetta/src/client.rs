@@ -1,248 +1,3950 @@
 // Copyright (c) Aptos
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::common::native_coin;
+#[cfg(feature = "debug")]
+use crate::common::decode_bcs;
+use crate::common::{
+    check_conflicting_signers, check_minimum_stake, derive_resource_account_address, encode_bcs,
+    encode_public_key, format_amount, get_gas_schedule_entries, get_minimum_stake, get_pool_stake,
+    get_resource, group_operations_by_sender, is_treasury_or_system_address, native_coin,
+    parse_amount, strip_hex_prefix, to_hex_lower, validate_construction_metadata,
+    validate_hex_bytes, validate_public_key, validate_single_sender_operations,
+};
+use crate::error::{ApiError, ApiResult};
 use crate::types::{
-    AccountBalanceRequest, AccountBalanceResponse, AccountIdentifier, Amount, BlockRequest,
-    BlockResponse, ConstructionCombineRequest, ConstructionCombineResponse,
+    AccountBalanceRequest, AccountBalanceResponse, AccountIdentifier, Allow, Amount, Block,
+    BlockRequest, BlockResponse, ConstructionCombineRequest, ConstructionCombineResponse,
     ConstructionDeriveRequest, ConstructionDeriveResponse, ConstructionHashRequest,
     ConstructionMetadata, ConstructionMetadataRequest, ConstructionMetadataResponse,
     ConstructionParseRequest, ConstructionParseResponse, ConstructionPayloadsRequest,
     ConstructionPayloadsResponse, ConstructionPreprocessRequest, ConstructionPreprocessResponse,
-    ConstructionSubmitRequest, ConstructionSubmitResponse, Error, MetadataRequest,
+    ConstructionSubmitRequest, ConstructionSubmitResponse, Currency, CurveType, Error,
+    MempoolResponse, MempoolTransactionRequest, MempoolTransactionResponse, MetadataRequest,
     NetworkIdentifier, NetworkListResponse, NetworkOptionsResponse, NetworkRequest,
-    NetworkStatusResponse, Operation, PreprocessMetadata, PublicKey, Signature, SignatureType,
-    TransactionIdentifier, TransactionIdentifierResponse,
+    NetworkStatusResponse, Operation, OperationStatusType, OperationType, PartialBlockIdentifier,
+    PreprocessMetadata, PublicKey, RotateKey, SearchTransactionsRequest,
+    SearchTransactionsResponse, Signature, SignatureType, SigningPayload, SyncStatus,
+    Transaction as RosettaTransaction, TransactionIdentifier, TransactionIdentifierResponse,
+    TransactionType,
 };
 use anyhow::anyhow;
-use aptos_crypto::ed25519::Ed25519PrivateKey;
-use aptos_crypto::SigningKey;
-use aptos_crypto::{PrivateKey, ValidCryptoMaterialStringExt};
-use aptos_rest_client::aptos_api_types::mime_types::JSON;
+use aptos_crypto::ed25519::{
+    Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature, ED25519_SIGNATURE_LENGTH,
+};
+use aptos_crypto::multi_ed25519::MultiEd25519PublicKey;
+use aptos_crypto::SigningKey as _;
+use aptos_crypto::{hash::CryptoHash, HashValue, PrivateKey, ValidCryptoMaterialStringExt};
+use aptos_rest_client::aptos_api_types::{
+    mime_types::JSON, Address, HexEncodedBytes, MoveModuleBytecode, Transaction as RestTransaction,
+    TransactionData, U128, U64,
+};
+use aptos_rest_client::{Resource as AccountResource, State};
+use aptos_sdk::move_types::language_storage::TypeTag;
 use aptos_types::account_address::AccountAddress;
-use aptos_types::transaction::RawTransaction;
-use reqwest::{header::CONTENT_TYPE, Client as ReqwestClient};
-use serde::{de::DeserializeOwned, Serialize};
-use std::collections::HashMap;
-use std::convert::TryInto;
+use aptos_types::transaction::{
+    authenticator::AuthenticationKey, RawTransaction, SignedTransaction, Transaction,
+};
+use futures::{Future, Stream, StreamExt};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use rand::Rng;
+use reqwest::{
+    header::{CONTENT_TYPE, RETRY_AFTER},
+    Client as ReqwestClient, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt::Debug;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
 use url::Url;
 
-/// Client for testing & interacting with a Rosetta service
-#[derive(Debug, Clone)]
-pub struct RosettaClient {
-    address: Url,
-    inner: ReqwestClient,
+/// Converts an error from the server to an expiration error, if it is one
+fn expiry_error(err: &anyhow::Error) -> Option<ApiError> {
+    let error = err.downcast_ref::<Error>()?;
+    let details = error.details.as_ref().map(|inner| inner.details.clone());
+    if error.code == ApiError::TransactionExpired(None).code() {
+        Some(ApiError::TransactionExpired(details))
+    } else if error.code == ApiError::InvalidExpiryTime(None).code() {
+        Some(ApiError::InvalidExpiryTime(details))
+    } else {
+        None
+    }
 }
 
-impl RosettaClient {
-    pub fn new(address: Url) -> RosettaClient {
-        RosettaClient {
-            address,
-            inner: ReqwestClient::new(),
-        }
+/// Converts a node that's fallen too far behind its sync target to a consensus failure
+fn consensus_failure(sync_status: &SyncStatus) -> Option<ApiError> {
+    let lag = sync_status.blocks_behind()?;
+    if lag > CONSENSUS_LAG_THRESHOLD {
+        Some(ApiError::ConsensusFailure(Some(format!(
+            "Node is {} blocks behind its sync target",
+            lag
+        ))))
+    } else {
+        None
     }
+}
 
-    pub async fn account_balance(
-        &self,
-        request: &AccountBalanceRequest,
-    ) -> anyhow::Result<AccountBalanceResponse> {
-        self.make_call("account/balance", request).await
+/// Checks a REST API response came from the same chain as the network
+fn check_chain_id(network_identifier: &NetworkIdentifier, state: &State) -> anyhow::Result<()> {
+    if network_identifier.chain_id()?.id() != state.chain_id {
+        return Err(ApiError::ChainIdMismatch.into());
     }
+    Ok(())
+}
 
-    pub async fn block(&self, request: &BlockRequest) -> anyhow::Result<BlockResponse> {
-        self.make_call("block", request).await
-    }
+/// Events emitted by a transaction, in the order they were emitted
+fn transaction_events(transaction: RestTransaction) -> Vec<RosettaEvent> {
+    let (version, events) = match transaction {
+        RestTransaction::UserTransaction(txn) => (txn.info.version.0, txn.events),
+        RestTransaction::GenesisTransaction(txn) => (txn.info.version.0, txn.events),
+        RestTransaction::BlockMetadataTransaction(txn) => (txn.info.version.0, txn.events),
+        _ => return vec![],
+    };
+    events
+        .into_iter()
+        .map(|event| RosettaEvent {
+            version,
+            sequence_number: event.sequence_number.0,
+            event_type: event.typ.to_string(),
+            data: event.data,
+        })
+        .collect()
+}
 
-    pub async fn combine(
-        &self,
-        request: &ConstructionCombineRequest,
-    ) -> anyhow::Result<ConstructionCombineResponse> {
-        self.make_call("construction/combine", request).await
+/// Pages through the events of the transactions from `first_version` to `last_version`
+///
+/// `fetch_events` returns the events of each transaction in a range, given its start and length
+fn paginate_events<F, Fut>(
+    first_version: u64,
+    last_version: u64,
+    page_size: usize,
+    fetch_events: F,
+) -> impl Stream<Item = anyhow::Result<Vec<RosettaEvent>>>
+where
+    F: FnMut(u64, u16) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<Vec<RosettaEvent>>>>,
+{
+    futures::stream::try_unfold(
+        (first_version, Vec::new(), fetch_events),
+        move |(mut next_version, mut events, mut fetch_events)| async move {
+            // Fill a page, unless the transactions run out first
+            while events.len() < page_size && next_version <= last_version {
+                let limit = u16::try_from(last_version - next_version + 1)
+                    .unwrap_or(u16::MAX)
+                    .min(TRANSACTIONS_PAGE_SIZE);
+                let transactions = fetch_events(next_version, limit).await?;
+                if transactions.is_empty() {
+                    break;
+                }
+                next_version += transactions.len() as u64;
+                events.extend(transactions.into_iter().flatten());
+            }
+
+            if events.is_empty() {
+                return Ok(None);
+            }
+            let remaining = events.split_off(page_size.min(events.len()));
+            Ok(Some((events, (next_version, remaining, fetch_events))))
+        },
+    )
+}
+
+/// Checks that each block's parent is the block before it, `blocks` must be in order
+fn check_block_linkage(blocks: &[Block]) -> Vec<ChainError> {
+    blocks
+        .windows(2)
+        .filter_map(|pair| {
+            let (parent, block) = (&pair[0], &pair[1]);
+            if block.parent_block_identifier.hash != parent.block_identifier.hash {
+                Some(ChainError {
+                    block_index: block.block_identifier.index,
+                    expected_parent_hash: parent.block_identifier.hash.clone(),
+                    actual_parent_hash: block.parent_block_identifier.hash.clone(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Whether the server's Rosetta version is at least [`MIN_ROSETTA_VERSION`]
+fn is_supported_rosetta_version(server_version: &str) -> bool {
+    fn parse(version: &str) -> Option<Vec<u64>> {
+        version
+            .split('.')
+            .map(|part| part.parse::<u64>().ok())
+            .collect()
     }
 
-    pub async fn derive(
-        &self,
-        request: &ConstructionDeriveRequest,
-    ) -> anyhow::Result<ConstructionDeriveResponse> {
-        self.make_call("construction/derive", request).await
+    match (parse(server_version), parse(MIN_ROSETTA_VERSION)) {
+        (Some(server_version), Some(min_version)) => server_version >= min_version,
+        _ => false,
     }
+}
 
-    pub async fn hash(
-        &self,
-        request: &ConstructionHashRequest,
-    ) -> anyhow::Result<TransactionIdentifierResponse> {
-        self.make_call("construction/hash", request).await
+/// Converts an amount in base units with a rate of `numerator / denominator`, rounding down
+fn apply_exchange_rate(value: i128, numerator: u64, denominator: u64) -> ApiResult<i128> {
+    if denominator == 0 {
+        return Err(ApiError::UnsupportedCurrency(Some(
+            "Exchange rate has a denominator of 0".to_string(),
+        )));
     }
+    value
+        .checked_mul(i128::from(numerator))
+        .map(|value| value / i128::from(denominator))
+        .ok_or_else(|| {
+            ApiError::AmountOverflow(Some(format!("{} * {} / {}", value, numerator, denominator)))
+        })
+}
 
-    pub async fn metadata(
-        &self,
-        request: &ConstructionMetadataRequest,
-    ) -> anyhow::Result<ConstructionMetadataResponse> {
-        self.make_call("construction/metadata", request).await
+/// Formats an amount of the native coin in APT rather than octas
+fn octas_to_apt(octas: u64) -> String {
+    const OCTAS_PER_APT: u64 = 100_000_000;
+    format!("{}.{:08}", octas / OCTAS_PER_APT, octas % OCTAS_PER_APT)
+}
+
+/// How far in the future transactions expire, when the caller doesn't choose
+const EXPIRY_WINDOW: Duration = Duration::from_secs(60);
+
+/// How many blocks a node can be behind its sync target before it's considered out of consensus
+pub const CONSENSUS_LAG_THRESHOLD: u64 = 100;
+
+/// Oldest Rosetta version of the server that the client is compatible with
+pub const MIN_ROSETTA_VERSION: &str = "1.4.12";
+
+/// Maximum number of blocks [`RosettaClient::gas_price_history`] fetches
+pub const MAX_GAS_PRICE_HISTORY_BLOCKS: usize = 100;
+
+/// Number of transactions fetched at a time from the REST API, when paging through a block
+const TRANSACTIONS_PAGE_SIZE: u16 = 100;
+
+/// Maximum number of preprocess calls in flight at once for [`RosettaClient::preprocess_batch`]
+const PREPROCESS_BATCH_CONCURRENCY: usize = 16;
+
+/// Maximum number of blocks fetched at once, when scanning a range of blocks
+const BLOCK_FETCH_CONCURRENCY: usize = 16;
+
+/// Maximum number of stake pools [`RosettaClient::active_validators`] fetches at once
+const ACTIVE_VALIDATORS_CONCURRENCY: usize = 50;
+
+/// How long a coin's total supply is cached for
+const SUPPLY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// How long the cost of creating an account is cached for, it only changes through governance
+const ACCOUNT_CREATION_COST_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// How long table items at a past ledger version are cached for
+const TABLE_ITEM_CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// How long the gas schedule is cached for, it's also refetched whenever the epoch changes
+const GAS_SCHEDULE_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// How long the currencies a server supports are cached for
+const SUPPORTED_CURRENCIES_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Errors from waiting on the chain to reach a given state
+#[derive(Debug, thiserror::Error)]
+pub enum WaitForTransactionError {
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+}
+
+/// Hands out sequence numbers locally, so that many transactions can be built concurrently
+/// without asking the server for a sequence number each time
+///
+/// Sequence numbers are fetched from the server the first time an account is seen
+#[derive(Debug, Clone)]
+pub struct NonceManager {
+    client: RosettaClient,
+    network_identifier: NetworkIdentifier,
+    nonces: Arc<RwLock<HashMap<AccountAddress, Arc<AtomicU64>>>>,
+}
+
+impl NonceManager {
+    pub fn new(client: RosettaClient, network_identifier: NetworkIdentifier) -> NonceManager {
+        NonceManager {
+            client,
+            network_identifier,
+            nonces: Arc::new(RwLock::new(HashMap::new())),
+        }
     }
 
-    pub async fn parse(
-        &self,
-        request: &ConstructionParseRequest,
-    ) -> anyhow::Result<ConstructionParseResponse> {
-        self.make_call("construction/parse", request).await
+    /// Returns the next unused sequence number for the account
+    pub async fn next_nonce(&self, account: AccountAddress) -> anyhow::Result<u64> {
+        if let Some(nonce) = self.nonces.read().unwrap().get(&account) {
+            return Ok(nonce.fetch_add(1, Ordering::SeqCst));
+        }
+
+        let sequence_number = self
+            .client
+            .account_sequence_number(self.network_identifier.clone(), account)
+            .await?;
+
+        // Another task may have initialized the account while we were fetching, so use theirs
+        let mut nonces = self.nonces.write().unwrap();
+        let nonce = nonces
+            .entry(account)
+            .or_insert_with(|| Arc::new(AtomicU64::new(sequence_number)));
+        Ok(nonce.fetch_add(1, Ordering::SeqCst))
     }
 
-    pub async fn payloads(
-        &self,
-        request: &ConstructionPayloadsRequest,
-    ) -> anyhow::Result<ConstructionPayloadsResponse> {
-        self.make_call("construction/payloads", request).await
+    /// Resets the account's next sequence number to the last one confirmed on chain
+    ///
+    /// This should be called when a submission fails, so that the gap it leaves is filled
+    pub fn reset_nonce(&self, account: AccountAddress, confirmed_seq: u64) {
+        let mut nonces = self.nonces.write().unwrap();
+        match nonces.get(&account) {
+            Some(nonce) => nonce.store(confirmed_seq, Ordering::SeqCst),
+            None => {
+                nonces.insert(account, Arc::new(AtomicU64::new(confirmed_seq)));
+            }
+        }
     }
+}
 
-    pub async fn preprocess(
-        &self,
-        request: &ConstructionPreprocessRequest,
-    ) -> anyhow::Result<ConstructionPreprocessResponse> {
-        self.make_call("construction/preprocess", request).await
+/// Supply of a coin, as tracked onchain
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CoinSupply {
+    /// Total amount of the coin in existence
+    pub total: u128,
+    /// Amount of the coin in circulation, if it's tracked separately from the total
+    pub circulating: Option<u128>,
+    /// Maximum amount of the coin that can exist, if it's limited
+    pub max: Option<u128>,
+}
+
+/// An account as it was at a ledger version
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountState {
+    /// Balance of the native coin in octas
+    pub balance: u128,
+    pub sequence_number: u64,
+    pub resources: Vec<AccountResource>,
+}
+
+/// How an account's balance of a currency changed over a range of blocks
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BalanceChangeSummary {
+    /// Received minus sent, which is the change in the balance
+    pub net_change: i128,
+    pub total_received: u128,
+    /// Everything taken from the balance, including fees
+    pub total_sent: u128,
+    /// Number of transactions that changed the balance
+    pub transaction_count: usize,
+}
+
+/// Sums the successful changes to the account's balance of the currency in the transactions
+fn summarize_balance_changes<'a>(
+    account: AccountAddress,
+    currency: &Currency,
+    transactions: impl IntoIterator<Item = &'a RosettaTransaction>,
+) -> anyhow::Result<BalanceChangeSummary> {
+    let success = OperationStatusType::Success.to_string();
+    let mut summary = BalanceChangeSummary::default();
+    for transaction in transactions {
+        let mut changed = false;
+        for operation in &transaction.operations {
+            // Sub accounts e.g. stake aren't part of the account's balance
+            let (account_identifier, amount) = match (&operation.account, &operation.amount) {
+                (Some(account_identifier), Some(amount))
+                    if account_identifier.sub_account.is_none()
+                        && &amount.currency == currency
+                        && operation.status.as_ref() == Some(&success) =>
+                {
+                    (account_identifier, amount)
+                }
+                _ => continue,
+            };
+            if account_identifier.account_address()? != account {
+                continue;
+            }
+
+            let value = parse_amount(amount)?;
+            if value >= 0 {
+                summary.total_received += value as u128;
+            } else {
+                summary.total_sent += value.unsigned_abs();
+            }
+            summary.net_change += value;
+            changed = true;
+        }
+        if changed {
+            summary.transaction_count += 1;
+        }
     }
+    Ok(summary)
+}
 
-    pub async fn submit(
-        &self,
-        request: &ConstructionSubmitRequest,
-    ) -> anyhow::Result<ConstructionSubmitResponse> {
-        self.make_call("construction/submit", request).await
+/// A simple cache where entries expire after a fixed duration
+#[derive(Debug, Clone)]
+struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Arc<RwLock<HashMap<K, (Instant, V)>>>,
+}
+
+impl<K: Eq + std::hash::Hash, V: Clone> TtlCache<K, V> {
+    fn new(ttl: Duration) -> Self {
+        TtlCache {
+            ttl,
+            entries: Arc::new(RwLock::new(HashMap::new())),
+        }
     }
 
-    pub async fn network_list(&self) -> anyhow::Result<NetworkListResponse> {
-        self.make_call("network/list", &MetadataRequest {}).await
+    fn get(&self, key: &K) -> Option<V> {
+        self.entries
+            .read()
+            .unwrap()
+            .get(key)
+            .filter(|(inserted, _)| inserted.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
     }
 
-    pub async fn network_options(
-        &self,
-        request: &NetworkRequest,
-    ) -> anyhow::Result<NetworkOptionsResponse> {
-        self.make_call("network/options", request).await
+    fn insert(&self, key: K, value: V) {
+        self.entries
+            .write()
+            .unwrap()
+            .insert(key, (Instant::now(), value));
     }
 
-    pub async fn network_status(
-        &self,
-        request: &NetworkRequest,
-    ) -> anyhow::Result<NetworkStatusResponse> {
-        self.make_call("network/status", request).await
+    fn clear(&self) {
+        self.entries.write().unwrap().clear();
     }
+}
 
-    async fn make_call<'a, I: Serialize + Debug, O: DeserializeOwned>(
-        &'a self,
-        path: &'static str,
-        request: &'a I,
-    ) -> anyhow::Result<O> {
-        let response = self
-            .inner
-            .post(self.address.join(path)?)
-            .header(CONTENT_TYPE, JSON)
-            .body(serde_json::to_string(request)?)
-            .send()
-            .await?;
+/// The gas fields of a metadata response, which can be reused across transactions
+#[derive(Clone, Debug)]
+struct CachedGas {
+    max_gas: u64,
+    gas_price_per_unit: u64,
+    min_gas_price_per_unit: Option<u64>,
+    suggested_fee: Option<Vec<Amount>>,
+}
 
-        if !response.status().is_success() {
-            let error: Error = response.json().await?;
-            return Err(anyhow!("Failed API with: {:?}", error));
+/// Caches construction metadata gas fields, since gas prices change slowly
+///
+/// Entries are keyed by network, a hash of the operation types and accounts, and the fees
+/// requested, as the gas price is derived from the fee multiplier.  Only the gas fields are
+/// cached, and the cache is only used when the caller supplies the sequence number e.g. from a
+/// [`NonceManager`].  Otherwise the server is asked every time, so it can look up the sequence
+/// number and run its checks on the accounts.
+#[derive(Debug, Clone)]
+pub struct MetadataCache {
+    cache: TtlCache<(NetworkIdentifier, HashValue, u64, u32), CachedGas>,
+}
+
+impl MetadataCache {
+    pub fn new(ttl: Duration) -> MetadataCache {
+        MetadataCache {
+            cache: TtlCache::new(ttl),
         }
+    }
 
-        Ok(response.json().await?)
+    /// Hashes the parts of the operations that determine the metadata
+    pub fn operation_type_hash(operations: &[Operation]) -> HashValue {
+        let mut bytes = Vec::new();
+        for operation in operations {
+            bytes.extend(operation.operation_type.as_bytes());
+            if let Some(ref account) = operation.account {
+                bytes.extend(account.address.as_bytes());
+            }
+            // Separates each operation, so different splits don't hash the same
+            bytes.push(0);
+        }
+        HashValue::sha3_256_of(&bytes)
     }
 
-    pub async fn create_account(
+    /// Returns a response built from the cached gas fields and the given sequence number, or
+    /// fetches one and caches its gas fields
+    ///
+    /// Without a sequence number, the response is always fetched.  The server's gas price and max
+    /// gas depend on the fees asked for, so they're part of the key
+    pub async fn get_or_fetch<F, Fut>(
         &self,
         network_identifier: &NetworkIdentifier,
-        private_key: &Ed25519PrivateKey,
-        new_account: AccountAddress,
-        expiry_time_secs: u64,
+        operations: &[Operation],
+        max_fee: u64,
+        fee_multiplier: u32,
         sequence_number: Option<u64>,
-    ) -> anyhow::Result<TransactionIdentifier> {
-        let sender = self
-            .get_account_address(network_identifier.clone(), private_key)
-            .await?;
-        let mut keys = HashMap::new();
-        keys.insert(sender, private_key);
+        fetch: F,
+    ) -> anyhow::Result<ConstructionMetadataResponse>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = anyhow::Result<ConstructionMetadataResponse>>,
+    {
+        let key = (
+            network_identifier.clone(),
+            MetadataCache::operation_type_hash(operations),
+            max_fee,
+            fee_multiplier,
+        );
+        if let Some(sequence_number) = sequence_number {
+            if let Some(gas) = self.cache.get(&key) {
+                return Ok(ConstructionMetadataResponse {
+                    metadata: ConstructionMetadata {
+                        sequence_number,
+                        max_gas: gas.max_gas,
+                        gas_price_per_unit: gas.gas_price_per_unit,
+                        expiry_time_secs: None,
+                        min_gas_price_per_unit: gas.min_gas_price_per_unit,
+                    },
+                    suggested_fee: gas.suggested_fee,
+                });
+            }
+        }
 
-        // A create account transaction is just a Create account operation
-        let operations = vec![Operation::create_account(0, None, new_account, sender)];
+        let response = fetch().await?;
+        self.cache.insert(
+            key,
+            CachedGas {
+                max_gas: response.metadata.max_gas,
+                gas_price_per_unit: response.metadata.gas_price_per_unit,
+                min_gas_price_per_unit: response.metadata.min_gas_price_per_unit,
+                suggested_fee: response.suggested_fee.clone(),
+            },
+        );
+        Ok(response)
+    }
 
-        self.submit_operations(
-            network_identifier.clone(),
-            &keys,
-            operations,
-            expiry_time_secs,
-            sequence_number,
-        )
-        .await
+    /// Drops all cached metadata e.g. when gas prices are stale
+    pub fn invalidate(&self) {
+        self.cache.clear();
     }
+}
 
-    pub async fn transfer(
-        &self,
-        network_identifier: &NetworkIdentifier,
-        private_key: &Ed25519PrivateKey,
-        receiver: AccountAddress,
-        amount: u64,
-        expiry_time_secs: u64,
-        sequence_number: Option<u64>,
-    ) -> anyhow::Result<TransactionIdentifier> {
-        let sender = self
-            .get_account_address(network_identifier.clone(), private_key)
-            .await?;
-        let mut keys = HashMap::new();
-        keys.insert(sender, private_key);
+/// Decoded view of an unsigned transaction, for debugging transaction construction
+#[cfg(feature = "debug")]
+#[derive(Clone, Debug)]
+pub struct UnsignedTransactionDebug {
+    /// The decoded transaction
+    pub raw_transaction: RawTransaction,
+    /// Hex encoded message that must be signed for the transaction
+    pub signing_message_hex: String,
+    /// Operations that the transaction should parse to
+    pub expected_operations: Vec<Operation>,
+}
 
-        // A transfer operation is made up of a withdraw and a deposit
-        let operations = vec![
-            Operation::withdraw(0, None, sender, native_coin(), amount),
-            Operation::deposit(1, None, receiver, native_coin(), amount),
-        ];
+/// Cost of creating a new account
+///
+/// There's no separate storage deposit, the new account's storage is paid for as gas
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AccountCreationCost {
+    /// Gas units used by a simulated `create_account` transaction
+    pub gas_used: u64,
+    /// Price per gas unit in octas
+    pub gas_unit_price: u64,
+    pub total_octas: u64,
+}
 
-        self.submit_operations(
-            network_identifier.clone(),
-            &keys,
-            operations,
-            expiry_time_secs,
-            sequence_number,
-        )
-        .await
-    }
+/// Estimated fee of a transaction
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeEstimate {
+    /// Estimated gas units used, this is the maximum the transaction may use
+    pub gas_used_estimate: u64,
+    /// Price per gas unit in octas
+    pub gas_unit_price: u64,
+    /// Total fee in octas
+    pub total_fee_octas: u64,
+    /// Total fee in APT, for display only.  Use `total_fee_octas` for any arithmetic
+    pub total_fee_apt: f64,
+}
 
-    /// Retrieves the account address from the derivation path if there isn't an overriding account specified
-    async fn get_account_address(
-        &self,
-        network_identifier: NetworkIdentifier,
-        private_key: &Ed25519PrivateKey,
-    ) -> anyhow::Result<AccountAddress> {
-        Ok(self
-            .derive_account(network_identifier, private_key.public_key().try_into()?)
-            .await?
-            .account_address()?)
+/// The on-chain gas parameters, from `0x1::gas_schedule::GasSchedule`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GasSchedule {
+    /// Gas parameters by name e.g. `instr.add` or `txn.min_transaction_gas_units`
+    pub entries: HashMap<String, u64>,
+}
+
+impl GasSchedule {
+    /// The cost of an operation by its gas parameter name, `None` if the schedule doesn't have it
+    pub fn cost_for_operation(&self, op: &str) -> Option<u64> {
+        self.entries.get(op).copied()
     }
+}
 
-    /// Submits the operations to the blockchain
-    async fn submit_operations(
-        &self,
-        network_identifier: NetworkIdentifier,
-        keys: &HashMap<AccountAddress, &Ed25519PrivateKey>,
-        operations: Vec<Operation>,
-        expiry_time_secs: u64,
-        sequence_number: Option<u64>,
-    ) -> anyhow::Result<TransactionIdentifier> {
-        // Retrieve txn metadata
-        let (metadata, public_keys) = self
-            .metadata_for_ops(
-                network_identifier.clone(),
-                operations.clone(),
-                10000,
-                1,
-                expiry_time_secs,
-                sequence_number,
-                keys,
-            )
-            .await?;
+/// Gas prices paid by user transactions over recent blocks, all prices are in octas
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GasPriceHistory {
+    /// Block index and gas unit price of each user transaction
+    pub samples: Vec<(u64, u64)>,
+    pub mean: u64,
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
 
-        // Build the transaction, sign it, and submit it
-        let response = self
+impl GasPriceHistory {
+    fn from_samples(samples: Vec<(u64, u64)>) -> GasPriceHistory {
+        let mut prices: Vec<u64> = samples.iter().map(|(_, price)| *price).collect();
+        prices.sort_unstable();
+
+        // Nearest rank percentiles, so every percentile is a price that was actually paid
+        let percentile = |percent: usize| -> u64 {
+            if prices.is_empty() {
+                return 0;
+            }
+            let rank = (percent * prices.len() + 99) / 100;
+            prices[rank.saturating_sub(1)]
+        };
+        let mean = if prices.is_empty() {
+            0
+        } else {
+            (prices.iter().map(|price| *price as u128).sum::<u128>() / prices.len() as u128) as u64
+        };
+
+        GasPriceHistory {
+            mean,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            samples,
+        }
+    }
+}
+
+/// Kinds of accounts on chain
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AccountType {
+    Regular,
+    /// Account controlled by a module through its signer capability
+    ResourceAccount,
+    MultisigAccount,
+    Object,
+    /// Framework and other reserved addresses
+    System,
+    /// Nothing exists at the address
+    Unknown,
+}
+
+/// Where a governance proposal is in its lifecycle
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProposalStatus {
+    /// Voting is still open
+    Pending,
+    /// Voting closed with enough yes votes, and it can be resolved
+    Succeeded,
+    /// Voting closed without enough yes votes
+    Failed,
+    /// The proposal has been executed
+    Resolved,
+}
+
+/// A governance proposal, and its votes so far
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GovernanceProposal {
+    pub id: u64,
+    pub proposer: AccountAddress,
+    /// Location of the proposal's metadata, which describes it
+    pub title: String,
+    /// Hash of the proposal's metadata
+    pub description_hash: String,
+    pub yes_votes: u128,
+    pub no_votes: u128,
+    pub expiration_secs: u64,
+    /// Hex encoded hash of the script that executes the proposal
+    pub execution_hash: Option<String>,
+    pub status: ProposalStatus,
+}
+
+/// On-chain configuration of a validator
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidatorConfig {
+    pub consensus_pubkey: Vec<u8>,
+    pub network_addresses: Vec<u8>,
+    pub fullnode_addresses: Vec<u8>,
+    pub operator_address: AccountAddress,
+    pub voter_address: AccountAddress,
+}
+
+impl ValidatorConfig {
+    /// The consensus public key as a `0x` prefixed hex string
+    pub fn consensus_pubkey_hex(&self) -> String {
+        format!("0x{}", hex::encode(&self.consensus_pubkey))
+    }
+}
+
+/// A validator in the active validator set, with its stake
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidatorSummary {
+    pub address: AccountAddress,
+    /// Voting power for the current epoch, which is fixed at the start of the epoch
+    pub voting_power: u64,
+    /// Stake currently active in the validator's stake pool
+    pub active_stake: u64,
+    pub config: ValidatorConfig,
+}
+
+/// A Move module's ABI and bytecode
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleMetadata {
+    pub abi: serde_json::Value,
+    /// Hex encoded bytecode of the module
+    pub bytecode_hex: String,
+    /// Names of the functions that can be called from outside the module
+    pub exposed_functions: Vec<String>,
+}
+
+/// An event emitted to an account's event handle
+#[derive(Clone, Debug, PartialEq)]
+pub struct RosettaEvent {
+    /// Ledger version of the transaction that emitted the event
+    pub version: u64,
+    pub sequence_number: u64,
+    pub event_type: String,
+    pub data: serde_json::Value,
+}
+
+/// A block whose parent hash doesn't match the hash of the block before it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChainError {
+    pub block_index: u64,
+    /// Hash of the block at `block_index - 1`
+    pub expected_parent_hash: String,
+    /// Parent hash reported by the block at `block_index`
+    pub actual_parent_hash: String,
+}
+
+/// Which types of transactions to keep when filtering a block
+///
+/// Genesis counts as a metadata transaction, as it isn't sent by a user
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TransactionFilter {
+    pub user: bool,
+    pub metadata: bool,
+    pub state_checkpoint: bool,
+}
+
+impl TransactionFilter {
+    /// Whether a transaction of the type passes the filter, transactions without a type never do
+    pub fn matches(&self, transaction: &RosettaTransaction) -> bool {
+        match transaction
+            .metadata
+            .map(|metadata| metadata.transaction_type)
+        {
+            Some(TransactionType::User) => self.user,
+            Some(TransactionType::BlockMetadata) | Some(TransactionType::Genesis) => self.metadata,
+            Some(TransactionType::StateCheckpoint) => self.state_checkpoint,
+            None => false,
+        }
+    }
+}
+
+/// Versions of the software behind a Rosetta server
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProtocolVersion {
+    pub rosetta_version: String,
+    pub node_version: String,
+    pub middleware_version: Option<String>,
+}
+
+/// Features that may or may not be supported depending on the server version
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RosettaFeature {
+    Staking,
+    KeyRotation,
+    /// Building and signing transfers without a node, with the `(OFFLINE)` construction endpoints
+    OfflineConstruction,
+}
+
+impl RosettaFeature {
+    /// Operation types, all of which must be allowed for the feature to be supported
+    fn operation_types(&self) -> &'static [OperationType] {
+        match self {
+            RosettaFeature::Staking => &[
+                OperationType::SetOperator,
+                OperationType::AddValidatorStake,
+                OperationType::UnlockStake,
+                OperationType::WithdrawInactiveStake,
+            ],
+            RosettaFeature::KeyRotation => &[OperationType::RotateKey],
+            RosettaFeature::OfflineConstruction => &[
+                OperationType::CreateAccount,
+                OperationType::Withdraw,
+                OperationType::Deposit,
+            ],
+        }
+    }
+
+    /// Checks the feature against the operation types in the server's network options
+    fn is_supported_by(&self, allow: &Allow) -> bool {
+        let allowed: HashSet<OperationType> = allow
+            .operation_types
+            .iter()
+            .filter_map(|operation_type| OperationType::from_str(operation_type).ok())
+            .collect();
+        self.operation_types()
+            .iter()
+            .all(|operation_type| allowed.contains(operation_type))
+    }
+}
+
+/// Limits on what a transaction may spend on gas
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MaxFeePolicy {
+    /// Maximum gas units the transaction may use
+    pub max_fee: u64,
+    /// Price per gas unit in octas
+    pub fee_multiplier: u32,
+}
+
+impl Default for MaxFeePolicy {
+    fn default() -> Self {
+        MaxFeePolicy {
+            max_fee: 10000,
+            fee_multiplier: 1,
+        }
+    }
+}
+
+/// How calls to the server are retried when they fail with a retriable error
+///
+/// The delay before each retry grows by `backoff_factor`, with up to 50% jitter on top
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryConfig {
+    /// Total attempts including the first, 1 never retries
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_delay: Duration,
+    pub backoff_factor: f64,
+    /// Time after the first attempt when no more retries are made
+    pub max_total_delay: Duration,
+}
+
+impl RetryConfig {
+    /// Delay before retrying after the given attempt, starting at 1, without jitter
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+        let delay = self.initial_delay.as_secs_f64() * self.backoff_factor.powi(exponent);
+        if delay.is_finite() && delay < self.max_total_delay.as_secs_f64() {
+            Duration::from_secs_f64(delay)
+        } else {
+            self.max_total_delay
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(200),
+            backoff_factor: 2.0,
+            max_total_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Keys of a MultiEd25519 account, which needs signatures from `threshold` of its keys
+///
+/// Not every key of the account needs a private key here, only enough to meet the threshold
+#[derive(Debug)]
+pub struct MultiKeySet {
+    public_key: MultiEd25519PublicKey,
+    private_keys: Vec<Ed25519PrivateKey>,
+}
+
+impl MultiKeySet {
+    /// Fails if the threshold can't be used with the public keys, or a private key isn't one of them
+    pub fn new(
+        public_keys: Vec<Ed25519PublicKey>,
+        private_keys: Vec<Ed25519PrivateKey>,
+        threshold: u8,
+    ) -> ApiResult<MultiKeySet> {
+        let public_key = MultiEd25519PublicKey::new(public_keys, threshold).map_err(|err| {
+            ApiError::InvalidInput(Some(format!("Invalid MultiEd25519 key set: {}", err)))
+        })?;
+        if let Some(private_key) = private_keys
+            .iter()
+            .find(|key| !public_key.public_keys().contains(&key.public_key()))
+        {
+            return Err(ApiError::InvalidInput(Some(format!(
+                "Private key for {} isn't part of the MultiEd25519 key set",
+                private_key.public_key()
+            ))));
+        }
+
+        Ok(MultiKeySet {
+            public_key,
+            private_keys,
+        })
+    }
+
+    pub fn threshold(&self) -> u8 {
+        *self.public_key.threshold()
+    }
+
+    /// Address derived from the key set, which is the account's address if it hasn't rotated keys
+    pub fn account_address(&self) -> AccountAddress {
+        AuthenticationKey::multi_ed25519(&self.public_key).derived_address()
+    }
+}
+
+/// A private key that signs transactions for an account
+///
+/// Secp256k1 has no authenticator onchain, so only Ed25519 keys are supported
+#[derive(Clone, Copy, Debug)]
+pub enum SigningKey<'a> {
+    Ed25519(&'a Ed25519PrivateKey),
+    MultiEd25519(&'a MultiKeySet),
+}
+
+impl<'a> From<&'a Ed25519PrivateKey> for SigningKey<'a> {
+    fn from(key: &'a Ed25519PrivateKey) -> Self {
+        SigningKey::Ed25519(key)
+    }
+}
+
+impl<'a> From<&'a MultiKeySet> for SigningKey<'a> {
+    fn from(key_set: &'a MultiKeySet) -> Self {
+        SigningKey::MultiEd25519(key_set)
+    }
+}
+
+impl SigningKey<'_> {
+    /// Curve of the key, which the server uses to derive the account
+    pub fn curve_type(&self) -> CurveType {
+        match self {
+            SigningKey::Ed25519(_) | SigningKey::MultiEd25519(_) => CurveType::Edwards25519,
+        }
+    }
+
+    /// Type of the signatures the key produces
+    pub fn signature_type(&self) -> SignatureType {
+        match self {
+            SigningKey::Ed25519(_) | SigningKey::MultiEd25519(_) => SignatureType::Ed25519,
+        }
+    }
+
+    /// Public key in Rosetta's format, for a key set this is every key followed by the threshold
+    pub fn public_key(&self) -> anyhow::Result<PublicKey> {
+        let hex_bytes = match self {
+            SigningKey::Ed25519(key) => key.public_key().to_encoded_string()?,
+            SigningKey::MultiEd25519(key_set) => key_set.public_key.to_encoded_string()?,
+        };
+        Ok(PublicKey {
+            hex_bytes,
+            curve_type: self.curve_type(),
+        })
+    }
+
+    /// Signs the transaction with every private key, returning a signature for each
+    ///
+    /// Fails if a key set doesn't have enough private keys to meet its threshold
+    pub fn sign(
+        &self,
+        payload: &SigningPayload,
+        unsigned_transaction: &RawTransaction,
+    ) -> anyhow::Result<Vec<Signature>> {
+        let private_keys = match self {
+            SigningKey::Ed25519(key) => vec![*key],
+            SigningKey::MultiEd25519(key_set) => {
+                if key_set.private_keys.len() < usize::from(key_set.threshold()) {
+                    return Err(ApiError::UnsupportedSignatureCount(Some(
+                        key_set.private_keys.len(),
+                    ))
+                    .into());
+                }
+                key_set.private_keys.iter().collect()
+            }
+        };
+
+        private_keys
+            .into_iter()
+            .map(|private_key| {
+                let public_key = encode_public_key(&private_key.public_key())?;
+                validate_public_key(&public_key)?;
+                let hex_bytes = private_key.sign(unsigned_transaction).to_encoded_string()?;
+                validate_hex_bytes(&hex_bytes, ED25519_SIGNATURE_LENGTH, "signature")?;
+                Ok(Signature {
+                    signing_payload: payload.clone(),
+                    public_key,
+                    signature_type: self.signature_type(),
+                    hex_bytes,
+                })
+            })
+            .collect()
+    }
+}
+
+/// What a transaction will cost, and who must sign it, before any keys are needed
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstructionPreview {
+    pub signers: Vec<AccountIdentifier>,
+    pub estimated_fee: Amount,
+}
+
+/// A signed transaction from a [`ConstructionFlowBuilder`], ready to be submitted
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignedConstruction {
+    pub network_identifier: NetworkIdentifier,
+    /// Hex encoded, BCS encoded [`aptos_types::transaction::SignedTransaction`]
+    pub signed_transaction: String,
+}
+
+/// How far a transaction got through construction and simulation, without being submitted
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SimulationTestResult {
+    /// Whether the simulated transaction executed successfully
+    pub would_succeed: bool,
+    /// Gas units used by the simulated transaction
+    pub estimated_gas: u64,
+    /// Price per gas unit in octas the transaction was built with
+    pub gas_unit_price: u64,
+    /// Whether the unsigned transaction parsed back to the same operations
+    pub parse_valid: bool,
+    /// Whether the signed transaction combined, and parsed back to the same operations and signers
+    pub combine_valid: bool,
+    /// The stage that failed and why, `None` if every stage passed
+    pub error: Option<String>,
+}
+
+impl SimulationTestResult {
+    fn failed(mut self, stage: &str, err: impl std::fmt::Display) -> Self {
+        self.error = Some(format!("{} failed: {}", stage, err));
+        self
+    }
+}
+
+/// Builds a transaction one step at a time, for UIs that show the fee before asking for approval
+///
+/// Created with [`RosettaClient::construction_flow_builder`]
+#[derive(Debug, Clone)]
+pub struct ConstructionFlowBuilder {
+    client: RosettaClient,
+    network_identifier: NetworkIdentifier,
+    operations: Vec<Operation>,
+    fee_policy: MaxFeePolicy,
+    expiry_time_secs: Option<u64>,
+    sequence_number: Option<u64>,
+}
+
+/// Builder for a [`RosettaClient`]
+#[derive(Debug, Clone)]
+pub struct RosettaClientBuilder {
+    address: Url,
+    network: Option<NetworkIdentifier>,
+    rest_client: Option<aptos_rest_client::Client>,
+    indexer_url: Option<Url>,
+    price_oracle: Option<AccountAddress>,
+    metadata_cache_ttl: Option<Duration>,
+    fallback_addresses: Vec<Url>,
+    retry_config: Option<RetryConfig>,
+}
+
+impl RosettaClientBuilder {
+    pub fn new(address: Url) -> RosettaClientBuilder {
+        RosettaClientBuilder {
+            address,
+            network: None,
+            rest_client: None,
+            indexer_url: None,
+            price_oracle: None,
+            metadata_cache_ttl: None,
+            fallback_addresses: Vec::new(),
+            retry_config: None,
+        }
+    }
+
+    /// Sets the network the client uses by default
+    pub fn network(mut self, network: NetworkIdentifier) -> RosettaClientBuilder {
+        self.network = Some(network);
+        self
+    }
+
+    /// Adds a fullnode connection for lookups that go directly to the REST API
+    pub fn rest_client(mut self, rest_client: aptos_rest_client::Client) -> RosettaClientBuilder {
+        self.rest_client = Some(rest_client);
+        self
+    }
+
+    /// Adds an indexer GraphQL endpoint for queries across many accounts
+    pub fn indexer_url(mut self, indexer_url: Url) -> RosettaClientBuilder {
+        self.indexer_url = Some(indexer_url);
+        self
+    }
+
+    /// Sets the account that publishes exchange rates, see [`RosettaClient::convert_currency`]
+    pub fn price_oracle(mut self, price_oracle: AccountAddress) -> RosettaClientBuilder {
+        self.price_oracle = Some(price_oracle);
+        self
+    }
+
+    /// Caches construction metadata for the given duration, see [`MetadataCache`]
+    pub fn with_metadata_cache(mut self, ttl: Duration) -> RosettaClientBuilder {
+        self.metadata_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Adds servers to try in order when the main one is unhealthy, see
+    /// [`RosettaClient::submit_with_health_check`]
+    pub fn fallback_addresses(mut self, fallback_addresses: Vec<Url>) -> RosettaClientBuilder {
+        self.fallback_addresses = fallback_addresses;
+        self
+    }
+
+    /// Retries calls that fail with a retriable error, see [`RetryConfig`]
+    pub fn with_retry(mut self, retry_config: RetryConfig) -> RosettaClientBuilder {
+        self.retry_config = Some(retry_config);
+        self
+    }
+
+    /// Builds the client, checking the server's Rosetta version if a network is set
+    pub async fn build(self) -> anyhow::Result<RosettaClient> {
+        let client = self.build_unchecked();
+        if let Some(network) = client.network.clone() {
+            let server_version = client.protocol_version(network).await?.rosetta_version;
+            if !is_supported_rosetta_version(&server_version) {
+                return Err(ApiError::IncompatibleApiVersion(Some(format!(
+                    "Client requires Rosetta version {} or later, server is {}",
+                    MIN_ROSETTA_VERSION, server_version
+                )))
+                .into());
+            }
+        }
+
+        Ok(client)
+    }
+
+    fn build_unchecked(self) -> RosettaClient {
+        RosettaClient {
+            address: self.address,
+            inner: ReqwestClient::new(),
+            network: self.network,
+            rest_client: self.rest_client,
+            indexer_url: self.indexer_url,
+            price_oracle: self.price_oracle,
+            fallback_addresses: self.fallback_addresses,
+            retry_config: self.retry_config,
+            supply_cache: TtlCache::new(SUPPLY_CACHE_TTL),
+            operations_cache: Arc::new(RwLock::new(HashMap::new())),
+            block_counts_cache: Arc::new(RwLock::new(HashMap::new())),
+            block_transactions_cache: Arc::new(RwLock::new(HashMap::new())),
+            event_handle_cache: Arc::new(RwLock::new(HashMap::new())),
+            module_cache: Arc::new(RwLock::new(HashMap::new())),
+            options_cache: Arc::new(RwLock::new(HashMap::new())),
+            metadata_cache: self.metadata_cache_ttl.map(MetadataCache::new),
+            account_creation_cost_cache: TtlCache::new(ACCOUNT_CREATION_COST_CACHE_TTL),
+            supported_currencies_cache: TtlCache::new(SUPPORTED_CURRENCIES_CACHE_TTL),
+            table_item_cache: TtlCache::new(TABLE_ITEM_CACHE_TTL),
+            gas_schedule_cache: TtlCache::new(GAS_SCHEDULE_CACHE_TTL),
+        }
+    }
+}
+
+/// Client for testing & interacting with a Rosetta service
+#[derive(Debug, Clone)]
+pub struct RosettaClient {
+    address: Url,
+    inner: ReqwestClient,
+    /// Network used when the caller doesn't specify one
+    network: Option<NetworkIdentifier>,
+    /// Optional connection to a fullnode, for lookups that Rosetta doesn't provide
+    rest_client: Option<aptos_rest_client::Client>,
+    /// Optional indexer GraphQL endpoint, for queries across many accounts
+    indexer_url: Option<Url>,
+    /// Optional account that publishes exchange rates between currencies
+    price_oracle: Option<AccountAddress>,
+    /// Servers to submit to when the main one is unhealthy
+    fallback_addresses: Vec<Url>,
+    /// How failed calls are retried, `None` never retries
+    retry_config: Option<RetryConfig>,
+    /// Total supply by coin type
+    supply_cache: TtlCache<String, CoinSupply>,
+    /// Operations by transaction hash, committed transactions never change so these never expire
+    operations_cache: Arc<RwLock<HashMap<HashValue, Vec<Operation>>>>,
+    /// Transaction and operation counts by block index, committed blocks never change either
+    block_counts_cache: Arc<RwLock<HashMap<u64, (usize, usize)>>>,
+    /// Transactions by block index, for filtering the same block more than once
+    block_transactions_cache: Arc<RwLock<HashMap<u64, Vec<RosettaTransaction>>>>,
+    /// Event handles known to exist by account, handles can't be removed once created
+    event_handle_cache: Arc<RwLock<HashMap<AccountAddress, HashSet<String>>>>,
+    /// Modules by account and name, with the ledger version they were last read at
+    module_cache: Arc<RwLock<HashMap<(AccountAddress, String), (u64, ModuleMetadata)>>>,
+    /// Server options by network, these only change when the server is upgraded
+    options_cache: Arc<RwLock<HashMap<NetworkIdentifier, NetworkOptionsResponse>>>,
+    /// Construction metadata, only if enabled in the builder
+    metadata_cache: Option<MetadataCache>,
+    /// Cost of creating an account by network
+    account_creation_cost_cache: TtlCache<NetworkIdentifier, AccountCreationCost>,
+    /// Currencies the server supports by network
+    supported_currencies_cache: TtlCache<NetworkIdentifier, Vec<Currency>>,
+    /// Table items by handle, key and value types, key, and ledger version
+    table_item_cache: TtlCache<(String, String, String, String, u64), serde_json::Value>,
+    /// Gas schedule by network, with the epoch it was read in
+    gas_schedule_cache: TtlCache<NetworkIdentifier, (u64, GasSchedule)>,
+}
+
+impl RosettaClient {
+    pub fn new(address: Url) -> RosettaClient {
+        RosettaClientBuilder::new(address).build_unchecked()
+    }
+
+    pub fn builder(address: Url) -> RosettaClientBuilder {
+        RosettaClientBuilder::new(address)
+    }
+
+    /// Creates a client that retries calls failing with a retriable error, see [`RetryConfig`]
+    pub fn with_retry(address: Url, retry_config: RetryConfig) -> RosettaClient {
+        RosettaClientBuilder::new(address)
+            .with_retry(retry_config)
+            .build_unchecked()
+    }
+
+    /// Creates a client for another network, sharing this client's connection pool
+    ///
+    /// The REST client and indexer are kept as is, if the network is served by a different node,
+    /// replace the REST client with [`RosettaClient::with_rest_client`]
+    pub fn clone_with_network(&self, new_network: NetworkIdentifier) -> RosettaClient {
+        RosettaClient {
+            address: self.address.clone(),
+            // Cloning a reqwest client shares its connection pool
+            inner: self.inner.clone(),
+            network: Some(new_network),
+            rest_client: self.rest_client.clone(),
+            indexer_url: self.indexer_url.clone(),
+            price_oracle: self.price_oracle,
+            fallback_addresses: self.fallback_addresses.clone(),
+            retry_config: self.retry_config,
+            // Cached values are specific to a network, so they can't be shared
+            supply_cache: TtlCache::new(SUPPLY_CACHE_TTL),
+            operations_cache: Arc::new(RwLock::new(HashMap::new())),
+            block_counts_cache: Arc::new(RwLock::new(HashMap::new())),
+            block_transactions_cache: Arc::new(RwLock::new(HashMap::new())),
+            event_handle_cache: Arc::new(RwLock::new(HashMap::new())),
+            module_cache: Arc::new(RwLock::new(HashMap::new())),
+            // Options and metadata are already keyed by network
+            options_cache: self.options_cache.clone(),
+            metadata_cache: self.metadata_cache.clone(),
+            account_creation_cost_cache: self.account_creation_cost_cache.clone(),
+            supported_currencies_cache: self.supported_currencies_cache.clone(),
+            table_item_cache: TtlCache::new(TABLE_ITEM_CACHE_TTL),
+            gas_schedule_cache: self.gas_schedule_cache.clone(),
+        }
+    }
+
+    /// Network used when the caller doesn't specify one
+    pub fn default_network(&self) -> Option<&NetworkIdentifier> {
+        self.network.as_ref()
+    }
+
+    /// Adds a fullnode connection for lookups that go directly to the REST API
+    pub fn with_rest_client(mut self, rest_client: aptos_rest_client::Client) -> RosettaClient {
+        self.rest_client = Some(rest_client);
+        self
+    }
+
+    fn rest_client(&self) -> anyhow::Result<&aptos_rest_client::Client> {
+        self.rest_client
+            .as_ref()
+            .ok_or_else(|| ApiError::NodeIsOffline(None).into())
+    }
+
+    pub async fn account_balance(
+        &self,
+        request: &AccountBalanceRequest,
+    ) -> anyhow::Result<AccountBalanceResponse> {
+        self.make_call("account/balance", request).await
+    }
+
+    pub async fn block(&self, request: &BlockRequest) -> anyhow::Result<BlockResponse> {
+        self.make_call("block", request).await
+    }
+
+    pub async fn combine(
+        &self,
+        request: &ConstructionCombineRequest,
+    ) -> anyhow::Result<ConstructionCombineResponse> {
+        self.make_call("construction/combine", request).await
+    }
+
+    pub async fn derive(
+        &self,
+        request: &ConstructionDeriveRequest,
+    ) -> anyhow::Result<ConstructionDeriveResponse> {
+        self.make_call("construction/derive", request).await
+    }
+
+    pub async fn hash(
+        &self,
+        request: &ConstructionHashRequest,
+    ) -> anyhow::Result<TransactionIdentifierResponse> {
+        self.make_call("construction/hash", request).await
+    }
+
+    pub async fn metadata(
+        &self,
+        request: &ConstructionMetadataRequest,
+    ) -> anyhow::Result<ConstructionMetadataResponse> {
+        self.make_call("construction/metadata", request).await
+    }
+
+    pub async fn parse(
+        &self,
+        request: &ConstructionParseRequest,
+    ) -> anyhow::Result<ConstructionParseResponse> {
+        self.make_call("construction/parse", request).await
+    }
+
+    pub async fn payloads(
+        &self,
+        request: &ConstructionPayloadsRequest,
+    ) -> anyhow::Result<ConstructionPayloadsResponse> {
+        self.make_call("construction/payloads", request).await
+    }
+
+    pub async fn preprocess(
+        &self,
+        request: &ConstructionPreprocessRequest,
+    ) -> anyhow::Result<ConstructionPreprocessResponse> {
+        self.make_call("construction/preprocess", request).await
+    }
+
+    pub async fn search_transactions(
+        &self,
+        request: &SearchTransactionsRequest,
+    ) -> anyhow::Result<SearchTransactionsResponse> {
+        self.make_call("search/transactions", request)
+            .await
+            .map_err(not_enabled_as_offline)
+    }
+
+    pub async fn mempool(&self, request: &NetworkRequest) -> anyhow::Result<MempoolResponse> {
+        self.make_call("mempool", request)
+            .await
+            .map_err(not_enabled_as_offline)
+    }
+
+    pub async fn mempool_transaction(
+        &self,
+        request: &MempoolTransactionRequest,
+    ) -> anyhow::Result<MempoolTransactionResponse> {
+        self.make_call("mempool/transaction", request)
+            .await
+            .map_err(not_enabled_as_offline)
+    }
+
+    /// Retrieves the transactions from the account waiting in mempool
+    ///
+    /// Returns [`ApiError::NodeIsOffline`] if the server doesn't have the mempool endpoints
+    pub async fn pending_transactions_for_account(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+    ) -> anyhow::Result<Vec<TransactionIdentifier>> {
+        let transaction_identifiers = self
+            .mempool(&NetworkRequest {
+                network_identifier: network_identifier.clone(),
+            })
+            .await?
+            .transaction_identifiers;
+
+        // Mempool only lists hashes, so each transaction has to be fetched for its sender
+        let transactions = futures::future::try_join_all(transaction_identifiers.into_iter().map(
+            |transaction_identifier| {
+                self.mempool_transaction(&MempoolTransactionRequest {
+                    network_identifier: network_identifier.clone(),
+                    transaction_identifier,
+                })
+            },
+        ))
+        .await?;
+        Ok(transactions
+            .into_iter()
+            .filter(|response| {
+                group_operations_by_sender(&response.transaction.operations).contains_key(&account)
+            })
+            .map(|response| response.transaction.transaction_identifier)
+            .collect())
+    }
+
+    /// Retrieves the number of transactions waiting in mempool
+    ///
+    /// Returns [`ApiError::NodeIsOffline`] if the server doesn't have the mempool endpoints
+    pub async fn pending_transaction_count(
+        &self,
+        network_identifier: NetworkIdentifier,
+    ) -> anyhow::Result<usize> {
+        Ok(self
+            .mempool(&NetworkRequest { network_identifier })
+            .await?
+            .transaction_identifiers
+            .len())
+    }
+
+    /// Searches for transactions with operations on the account, a page at a time
+    pub async fn search_transactions_by_account(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+        limit: usize,
+        offset: usize,
+    ) -> anyhow::Result<SearchTransactionsResponse> {
+        self.search_transactions(&SearchTransactionsRequest {
+            network_identifier,
+            operator: None,
+            max_block: None,
+            offset: Some(u64::try_from(offset)?),
+            limit: Some(u64::try_from(limit)?),
+            transaction_identifier: None,
+            account_identifier: Some(account.into()),
+            currency: None,
+            status: None,
+            operation_type: None,
+            address: None,
+            success: None,
+        })
+        .await
+    }
+
+    pub async fn submit(
+        &self,
+        request: &ConstructionSubmitRequest,
+    ) -> anyhow::Result<ConstructionSubmitResponse> {
+        self.make_call("construction/submit", request).await
+    }
+
+    pub async fn network_list(&self) -> anyhow::Result<NetworkListResponse> {
+        self.make_call("network/list", &MetadataRequest {}).await
+    }
+
+    pub async fn network_options(
+        &self,
+        request: &NetworkRequest,
+    ) -> anyhow::Result<NetworkOptionsResponse> {
+        self.make_call("network/options", request).await
+    }
+
+    pub async fn network_status(
+        &self,
+        request: &NetworkRequest,
+    ) -> anyhow::Result<NetworkStatusResponse> {
+        self.make_call("network/status", request).await
+    }
+
+    /// Retrieves the versions of the server, and the node behind it
+    pub async fn protocol_version(
+        &self,
+        network_identifier: NetworkIdentifier,
+    ) -> anyhow::Result<ProtocolVersion> {
+        let version = self
+            .cached_network_options(network_identifier)
+            .await?
+            .version;
+        Ok(ProtocolVersion {
+            rosetta_version: version.rosetta_version,
+            node_version: version.node_version,
+            middleware_version: Some(version.middleware_version)
+                .filter(|middleware_version| !middleware_version.is_empty()),
+        })
+    }
+
+    /// Checks if the server supports a feature, based on the operations it allows
+    pub async fn supports_feature(
+        &self,
+        network_identifier: NetworkIdentifier,
+        feature: RosettaFeature,
+    ) -> anyhow::Result<bool> {
+        let options = self.cached_network_options(network_identifier).await?;
+        Ok(feature.is_supported_by(&options.allow))
+    }
+
+    /// Retrieves the network options, from the cache if they've been fetched before
+    async fn cached_network_options(
+        &self,
+        network_identifier: NetworkIdentifier,
+    ) -> anyhow::Result<NetworkOptionsResponse> {
+        if let Some(options) = self.options_cache.read().unwrap().get(&network_identifier) {
+            return Ok(options.clone());
+        }
+
+        let options = self
+            .network_options(&NetworkRequest {
+                network_identifier: network_identifier.clone(),
+            })
+            .await?;
+        self.options_cache
+            .write()
+            .unwrap()
+            .insert(network_identifier, options.clone());
+        Ok(options)
+    }
+
+    /// Retrieves the currencies that can be used in operations
+    ///
+    /// Servers that don't list their currencies only support the native coin.  Results are cached
+    /// for an hour
+    pub async fn list_supported_currencies(
+        &self,
+        network_identifier: NetworkIdentifier,
+    ) -> anyhow::Result<Vec<Currency>> {
+        if let Some(currencies) = self.supported_currencies_cache.get(&network_identifier) {
+            return Ok(currencies);
+        }
+
+        let options = self
+            .network_options(&NetworkRequest {
+                network_identifier: network_identifier.clone(),
+            })
+            .await?;
+        let currencies = match options.allow.currencies {
+            Some(currencies) => {
+                let mut seen = HashSet::new();
+                currencies
+                    .into_iter()
+                    .filter(|currency| seen.insert(currency.clone()))
+                    .collect()
+            }
+            None => vec![native_coin()],
+        };
+
+        self.supported_currencies_cache
+            .insert(network_identifier, currencies.clone());
+        Ok(currencies)
+    }
+
+    /// Checks that the server's node is healthy, and has reached the block if one is given
+    ///
+    /// A node that's more than [`CONSENSUS_LAG_THRESHOLD`] blocks behind its sync target fails
+    /// with [`ApiError::ConsensusFailure`], as its state may be inconsistent
+    pub async fn health_check(
+        &self,
+        network_identifier: NetworkIdentifier,
+        min_block_index: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let response = self
+            .inner
+            .get(self.address.join("-/healthy")?)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let body = response.bytes().await?;
+            return Err(ApiError::from_response(status, &content_type, body)
+                .into_error()
+                .into());
+        }
+
+        let status = self
+            .network_status(&NetworkRequest { network_identifier })
+            .await?;
+        if let Some(err) = status.sync_status.as_ref().and_then(consensus_failure) {
+            return Err(err.into());
+        }
+
+        if let Some(min_block_index) = min_block_index {
+            let block_index = status.current_block_identifier.index;
+            if block_index < min_block_index {
+                return Err(anyhow!(
+                    "Node is behind at block {}, expected at least {}",
+                    block_index,
+                    min_block_index
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieves the index of the latest block known to the server
+    pub async fn current_block_index(
+        &self,
+        network_identifier: NetworkIdentifier,
+    ) -> anyhow::Result<u64> {
+        Ok(self
+            .network_status(&NetworkRequest { network_identifier })
+            .await?
+            .current_block_identifier
+            .index)
+    }
+
+    /// Retrieves the gas prices paid by user transactions in the most recent blocks
+    ///
+    /// At most [`MAX_GAS_PRICE_HISTORY_BLOCKS`] blocks can be fetched.  Requires a REST client,
+    /// see [`RosettaClient::with_rest_client`]
+    pub async fn gas_price_history(
+        &self,
+        network_identifier: NetworkIdentifier,
+        last_n_blocks: usize,
+    ) -> anyhow::Result<GasPriceHistory> {
+        if last_n_blocks > MAX_GAS_PRICE_HISTORY_BLOCKS {
+            return Err(ApiError::InvalidInput(Some(format!(
+                "Too many blocks {}, maximum is {}",
+                last_n_blocks, MAX_GAS_PRICE_HISTORY_BLOCKS
+            )))
+            .into());
+        }
+        if last_n_blocks == 0 {
+            return Ok(GasPriceHistory::from_samples(vec![]));
+        }
+
+        let rest_client = self.rest_client()?;
+        let current_block_index = self.current_block_index(network_identifier).await?;
+        let first_block_index =
+            current_block_index.saturating_sub(u64::try_from(last_n_blocks)? - 1);
+        let blocks = futures::future::try_join_all(
+            (first_block_index..=current_block_index)
+                .map(|block_index| rest_client.get_block_by_height(block_index, true)),
+        )
+        .await?;
+
+        let samples = blocks
+            .into_iter()
+            .flat_map(|block| {
+                let block = block.into_inner();
+                let block_index = block.block_height.0;
+                block
+                    .transactions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(move |txn| match txn {
+                        aptos_rest_client::Transaction::UserTransaction(txn) => {
+                            Some((block_index, txn.request.gas_unit_price.0))
+                        }
+                        _ => None,
+                    })
+            })
+            .collect();
+
+        Ok(GasPriceHistory::from_samples(samples))
+    }
+
+    /// Retrieves the accounts with the largest balances of a currency, largest first
+    ///
+    /// Rosetta can't query across accounts, so this requires an indexer, see
+    /// [`RosettaClientBuilder::indexer_url`].  Returns [`ApiError::NodeIsOffline`] if the indexer
+    /// isn't configured or can't be reached.  Historical blocks also require a REST client.
+    pub async fn top_accounts_by_balance(
+        &self,
+        network_identifier: NetworkIdentifier,
+        currency: &Currency,
+        limit: usize,
+        block: Option<PartialBlockIdentifier>,
+    ) -> anyhow::Result<Vec<(AccountAddress, u128)>> {
+        /// Types for deserializing the indexer's response
+        #[derive(Deserialize)]
+        struct GraphQlResponse {
+            data: Option<CoinBalances>,
+        }
+        #[derive(Deserialize)]
+        struct CoinBalances {
+            #[serde(alias = "coin_balances")]
+            current_coin_balances: Vec<CoinBalance>,
+        }
+        #[derive(Deserialize)]
+        struct CoinBalance {
+            owner_address: String,
+            amount: serde_json::Value,
+        }
+
+        let indexer_url = self
+            .indexer_url
+            .as_ref()
+            .ok_or(ApiError::NodeIsOffline(None))?;
+        let coin_type = currency
+            .metadata
+            .as_ref()
+            .map(|metadata| metadata.move_type.clone())
+            .ok_or_else(|| ApiError::UnsupportedCurrency(Some(currency.symbol.clone())))?;
+
+        // Historical balances are the latest change to each account at or before the block
+        let query = match self.block_version(network_identifier, block).await? {
+            Some(version) => serde_json::json!({
+                "query": "query TopAccounts($coin_type: String, $version: bigint) {
+                    coin_balances(
+                        where: {coin_type: {_eq: $coin_type}, transaction_version: {_lte: $version}},
+                        distinct_on: owner_address,
+                        order_by: [{owner_address: asc}, {transaction_version: desc}]
+                    ) { owner_address amount }
+                }",
+                "variables": { "coin_type": coin_type, "version": version },
+            }),
+            None => serde_json::json!({
+                "query": "query TopAccounts($coin_type: String, $limit: Int) {
+                    current_coin_balances(
+                        where: {coin_type: {_eq: $coin_type}},
+                        order_by: {amount: desc},
+                        limit: $limit
+                    ) { owner_address amount }
+                }",
+                "variables": { "coin_type": coin_type, "limit": limit },
+            }),
+        };
+
+        let response: GraphQlResponse = self
+            .inner
+            .post(indexer_url.clone())
+            .json(&query)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|_| ApiError::NodeIsOffline(None))?
+            .json()
+            .await?;
+        let balances = response
+            .data
+            .ok_or_else(|| ApiError::InternalError(Some("Indexer query failed".to_string())))?
+            .current_coin_balances;
+
+        // Amounts are numeric, which may come back as either a number or a string
+        let mut accounts = Vec::new();
+        for balance in balances {
+            let amount = match balance.amount {
+                serde_json::Value::Number(amount) => amount.to_string(),
+                serde_json::Value::String(amount) => amount,
+                _ => return Err(ApiError::deserialization_failed("CoinBalance").into()),
+            };
+            accounts.push((
+                AccountAddress::from_hex_literal(&balance.owner_address)?,
+                u128::from_str(&amount)?,
+            ));
+        }
+        accounts.sort_by(|(_, left), (_, right)| right.cmp(left));
+        accounts.truncate(limit);
+        Ok(accounts)
+    }
+
+    /// Retrieves the current sequence number of an account
+    pub async fn account_sequence_number(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+    ) -> anyhow::Result<u64> {
+        Ok(self
+            .account_balance(&AccountBalanceRequest {
+                network_identifier,
+                account_identifier: account.into(),
+                block_identifier: None,
+                currencies: None,
+            })
+            .await?
+            .metadata
+            .sequence_number)
+    }
+
+    /// Retrieves an account's balance, sequence number, and resources at a ledger version
+    ///
+    /// Rosetta only tracks balances by block, so the balance and sequence number are as of the end
+    /// of the block containing the version, while the resources are at the version itself.
+    /// Requires a REST client, see [`RosettaClient::with_rest_client`]
+    pub async fn account_state_at_version(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+        version: u64,
+    ) -> anyhow::Result<AccountState> {
+        let rest_client = self.rest_client()?;
+
+        // Pruned blocks are only pruned because their versions are
+        let response = rest_client
+            .get_block_by_version(version, false)
+            .await
+            .map_err(|err| match ApiError::from(err) {
+                ApiError::BlockPruned(details) => ApiError::VersionPruned(details),
+                err => err,
+            })?;
+        check_chain_id(&network_identifier, response.state())?;
+        let block_index = response.into_inner().block_height.0;
+
+        let response = self
+            .account_balance(&AccountBalanceRequest {
+                network_identifier,
+                account_identifier: account.into(),
+                block_identifier: Some(PartialBlockIdentifier::block_index(block_index)),
+                currencies: Some(vec![native_coin()]),
+            })
+            .await?;
+        let balance = match response
+            .balances
+            .iter()
+            .find(|amount| amount.currency == native_coin())
+        {
+            Some(amount) => u128::from_str(&amount.value)?,
+            None => 0,
+        };
+
+        let resources = rest_client
+            .get_account_resources_at_version(account, version)
+            .await
+            .map_err(ApiError::from)?
+            .into_inner();
+
+        Ok(AccountState {
+            balance,
+            sequence_number: response.metadata.sequence_number,
+            resources,
+        })
+    }
+
+    /// Retrieves the ledger version a resource was read at, from the `X-Aptos-Ledger-Version` header
+    ///
+    /// The resource is read at the latest version, so comparing the version between a read and a
+    /// write tells whether the ledger may have moved on in between, and the write should be retried.
+    /// Requires a REST client, see [`RosettaClient::with_rest_client`]
+    pub async fn account_resource_version(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+        resource_type: &str,
+    ) -> anyhow::Result<u64> {
+        const ENCODE_CHARS: &AsciiSet = &CONTROLS.add(b'<').add(b'>');
+        let encoded_resource_type = utf8_percent_encode(resource_type, ENCODE_CHARS).to_string();
+        let response = self
+            .rest_client()?
+            .get_account_resource(account, &encoded_resource_type)
+            .await
+            .map_err(ApiError::from)?;
+        check_chain_id(&network_identifier, response.state())?;
+        let version = response.state().version;
+
+        if response.into_inner().is_some() {
+            Ok(version)
+        } else {
+            Err(ApiError::ResourceNotFound(Some(format!(
+                "{} not found at {}",
+                resource_type, account
+            )))
+            .into())
+        }
+    }
+
+    /// Checks that the payer has enough of the native coin to pay for the estimated gas
+    pub async fn check_gas_balance(
+        &self,
+        network_identifier: NetworkIdentifier,
+        payer: AccountAddress,
+        estimated_gas_cost: u64,
+    ) -> Result<(), ApiError> {
+        let response = self
+            .account_balance(&AccountBalanceRequest {
+                network_identifier,
+                account_identifier: payer.into(),
+                block_identifier: None,
+                currencies: Some(vec![native_coin()]),
+            })
+            .await;
+        // An account that doesn't exist has nothing to pay with
+        let available = match response {
+            Ok(response) => match response
+                .balances
+                .iter()
+                .find(|amount| amount.currency == native_coin())
+            {
+                Some(amount) => u64::from_str(&amount.value)?,
+                None => 0,
+            },
+            Err(err) => match err.downcast::<Error>() {
+                Ok(error) if error.code == ApiError::AccountNotFound(None).code() => 0,
+                Ok(error) => {
+                    return Err(ApiError::from_code(
+                        error.code,
+                        error.details.map(|details| details.details),
+                    ))
+                }
+                Err(err) => return Err(err.into()),
+            },
+        };
+
+        if available < estimated_gas_cost {
+            Err(ApiError::InsufficientGasBalance(Some(format!(
+                "Required {} APT, available {} APT",
+                octas_to_apt(estimated_gas_cost),
+                octas_to_apt(available)
+            ))))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Converts an amount to another currency, at the price oracle's latest exchange rate
+    ///
+    /// The oracle is set with [`RosettaClientBuilder::price_oracle`], and must publish a
+    /// `price_oracle::ExchangeRate<From, To> { numerator: u64, denominator: u64 }` resource for
+    /// the pair, where one base unit of `From` is worth `numerator / denominator` base units of
+    /// `To`.  Results are rounded down.  Requires a REST client, see
+    /// [`RosettaClient::with_rest_client`]
+    pub async fn convert_currency(
+        &self,
+        network_identifier: NetworkIdentifier,
+        from_amount: &Amount,
+        to_currency: &Currency,
+    ) -> anyhow::Result<Amount> {
+        /// Type for deserializing the exchange rate
+        #[derive(Deserialize)]
+        struct ExchangeRate {
+            numerator: U64,
+            denominator: U64,
+        }
+
+        if &from_amount.currency == to_currency {
+            return Ok(from_amount.clone());
+        }
+        let move_type = |currency: &Currency| {
+            currency
+                .metadata
+                .as_ref()
+                .map(|metadata| metadata.move_type.clone())
+                .ok_or_else(|| ApiError::UnsupportedCurrency(Some(currency.symbol.clone())))
+        };
+        let from_type = move_type(&from_amount.currency)?;
+        let to_type = move_type(to_currency)?;
+        let price_oracle = self.price_oracle.ok_or_else(|| {
+            ApiError::UnsupportedCurrency(Some("No price oracle is configured".to_string()))
+        })?;
+
+        let resource_type = format!(
+            "{}::price_oracle::ExchangeRate<{}, {}>",
+            price_oracle.to_hex_literal(),
+            from_type,
+            to_type
+        );
+        let exchange_rate: ExchangeRate = match self
+            .get_optional_resource(&network_identifier, price_oracle, &resource_type)
+            .await?
+        {
+            Some(exchange_rate) => serde_json::from_value(exchange_rate)
+                .map_err(|_| ApiError::deserialization_failed("ExchangeRate"))?,
+            None => {
+                return Err(ApiError::UnsupportedCurrency(Some(format!(
+                    "No exchange rate from {} to {}",
+                    from_amount.currency.symbol, to_currency.symbol
+                )))
+                .into())
+            }
+        };
+
+        let value = apply_exchange_rate(
+            parse_amount(from_amount)?,
+            exchange_rate.numerator.0,
+            exchange_rate.denominator.0,
+        )?;
+        Ok(format_amount(value, to_currency.clone()))
+    }
+
+    /// Retrieves the on-chain gas schedule
+    ///
+    /// The gas schedule can only change on reconfiguration, so it's cached until the epoch
+    /// changes, or for an hour at most.  Requires a REST client, see
+    /// [`RosettaClient::with_rest_client`]
+    pub async fn gas_schedule(
+        &self,
+        network_identifier: NetworkIdentifier,
+    ) -> anyhow::Result<GasSchedule> {
+        let rest_client = self.rest_client()?;
+        let state = rest_client
+            .get_ledger_information()
+            .await
+            .map_err(ApiError::from)?
+            .into_inner();
+        check_chain_id(&network_identifier, &state)?;
+        if let Some((epoch, gas_schedule)) = self.gas_schedule_cache.get(&network_identifier) {
+            if epoch == state.epoch {
+                return Ok(gas_schedule);
+            }
+        }
+
+        let gas_schedule = GasSchedule {
+            entries: get_gas_schedule_entries(rest_client, Some(state.version)).await?,
+        };
+        self.gas_schedule_cache
+            .insert(network_identifier, (state.epoch, gas_schedule.clone()));
+        Ok(gas_schedule)
+    }
+
+    /// Retrieves the supply of a coin, at the given block or the latest block
+    ///
+    /// Requires a REST client, see [`RosettaClient::with_rest_client`]
+    pub async fn get_coin_supply(
+        &self,
+        network_identifier: NetworkIdentifier,
+        currency: &Currency,
+        block: Option<PartialBlockIdentifier>,
+    ) -> anyhow::Result<CoinSupply> {
+        /// Type for deserializing a Move `Option`
+        #[derive(Deserialize)]
+        struct MoveOption<T> {
+            vec: Vec<T>,
+        }
+        #[derive(Deserialize)]
+        struct Integer {
+            value: U128,
+            limit: U128,
+        }
+        #[derive(Deserialize)]
+        struct Aggregator {
+            limit: U128,
+        }
+        #[derive(Deserialize)]
+        struct OptionalAggregator {
+            aggregator: MoveOption<Aggregator>,
+            integer: MoveOption<Integer>,
+        }
+        #[derive(Deserialize)]
+        struct CoinInfo {
+            supply: MoveOption<OptionalAggregator>,
+        }
+
+        let coin_type = currency
+            .metadata
+            .as_ref()
+            .map(|metadata| metadata.move_type.clone())
+            .ok_or_else(|| ApiError::UnsupportedCurrency(Some(currency.symbol.clone())))?;
+
+        // Only the latest supply is cached, historical lookups always go to the node
+        if block.is_none() {
+            if let Some(supply) = self.supply_cache.get(&coin_type) {
+                return Ok(supply);
+            }
+        }
+
+        let version = self
+            .block_version(network_identifier.clone(), block.clone())
+            .await?;
+        let coin_address = match TypeTag::from_str(&coin_type)? {
+            TypeTag::Struct(struct_tag) => struct_tag.address,
+            _ => return Err(ApiError::UnsupportedCurrency(Some(coin_type)).into()),
+        };
+
+        // Supply tracking must be set up onchain for supply to be meaningful
+        self.get_resource_at_version::<serde_json::Value>(
+            &network_identifier,
+            AccountAddress::ONE,
+            "0x1::coin::SupplyConfig",
+            version,
+        )
+        .await?;
+        let coin_info: CoinInfo = self
+            .get_resource_at_version(
+                &network_identifier,
+                coin_address,
+                &format!("0x1::coin::CoinInfo<{}>", coin_type),
+                version,
+            )
+            .await?;
+
+        let supply = match coin_info.supply.vec.into_iter().next() {
+            Some(supply) => match (
+                supply.integer.vec.into_iter().next(),
+                supply.aggregator.vec.into_iter().next(),
+            ) {
+                (Some(integer), _) => CoinSupply {
+                    total: integer.value.0,
+                    circulating: None,
+                    max: Some(integer.limit.0),
+                },
+                // Aggregator values live outside of the resource, and can't be read through the API
+                (None, Some(_)) => {
+                    return Err(ApiError::InternalError(Some(format!(
+                        "Parallelizable supply is not supported for {}",
+                        coin_type
+                    )))
+                    .into())
+                }
+                (None, None) => {
+                    return Err(ApiError::deserialization_failed("OptionalAggregator").into())
+                }
+            },
+            None => {
+                return Err(ApiError::ResourceNotFound(Some(format!(
+                    "Supply is not tracked for {}",
+                    coin_type
+                )))
+                .into())
+            }
+        };
+
+        if block.is_none() {
+            self.supply_cache.insert(coin_type, supply.clone());
+        }
+        Ok(supply)
+    }
+
+    /// Reconstructs the operations of a committed transaction from its hash
+    ///
+    /// Requires a REST client, see [`RosettaClient::with_rest_client`]
+    pub async fn get_operations_from_hash(
+        &self,
+        network_identifier: NetworkIdentifier,
+        hash: &str,
+    ) -> anyhow::Result<Vec<Operation>> {
+        let hash = HashValue::from_str(strip_hex_prefix(hash))
+            .map_err(|err| ApiError::InvalidInput(Some(err.to_string())))?;
+        if let Some(operations) = self.operations_cache.read().unwrap().get(&hash) {
+            return Ok(operations.clone());
+        }
+
+        let signed_transaction = match self
+            .rest_client()?
+            .get_transaction_by_hash_bcs(hash)
+            .await
+            .map_err(ApiError::from)?
+            .into_inner()
+        {
+            TransactionData::OnChain(txn) => match txn.transaction {
+                Transaction::UserTransaction(signed_transaction) => signed_transaction,
+                _ => {
+                    return Err(ApiError::TransactionParseError(Some(format!(
+                        "Transaction {} is not a user transaction",
+                        hash
+                    )))
+                    .into())
+                }
+            },
+            TransactionData::Pending(_) => return Err(ApiError::TransactionIsPending.into()),
+        };
+
+        let operations = self
+            .parse(&ConstructionParseRequest {
+                network_identifier,
+                signed: true,
+                transaction: encode_bcs(&signed_transaction)?,
+            })
+            .await?
+            .operations;
+        self.operations_cache
+            .write()
+            .unwrap()
+            .insert(hash, operations.clone());
+        Ok(operations)
+    }
+
+    /// Retrieves the number of transactions in a block
+    pub async fn block_transaction_count(
+        &self,
+        network_identifier: NetworkIdentifier,
+        block_id: PartialBlockIdentifier,
+    ) -> anyhow::Result<usize> {
+        Ok(self.block_counts(network_identifier, block_id).await?.0)
+    }
+
+    /// Retrieves the number of operations across all transactions in a block
+    pub async fn block_operation_count(
+        &self,
+        network_identifier: NetworkIdentifier,
+        block_id: PartialBlockIdentifier,
+    ) -> anyhow::Result<usize> {
+        Ok(self.block_counts(network_identifier, block_id).await?.1)
+    }
+
+    /// Retrieves the user transactions in a block
+    pub async fn block_user_transactions(
+        &self,
+        network_identifier: NetworkIdentifier,
+        block_id: PartialBlockIdentifier,
+    ) -> anyhow::Result<Vec<RosettaTransaction>> {
+        self.block_transactions_filtered(
+            network_identifier,
+            block_id,
+            TransactionFilter {
+                user: true,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Retrieves the metadata transaction that starts a block, if it has one
+    pub async fn block_metadata_transaction(
+        &self,
+        network_identifier: NetworkIdentifier,
+        block_id: PartialBlockIdentifier,
+    ) -> anyhow::Result<Option<RosettaTransaction>> {
+        Ok(self
+            .block_transactions_filtered(
+                network_identifier,
+                block_id,
+                TransactionFilter {
+                    metadata: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    /// Retrieves the transactions in a block that pass the filter
+    ///
+    /// The block is only fetched once, so it can be filtered many ways without downloading it again
+    pub async fn block_transactions_filtered(
+        &self,
+        network_identifier: NetworkIdentifier,
+        block_id: PartialBlockIdentifier,
+        filter: TransactionFilter,
+    ) -> anyhow::Result<Vec<RosettaTransaction>> {
+        // Blocks can only be looked up in the cache by index
+        if let PartialBlockIdentifier {
+            index: Some(index),
+            hash: None,
+        } = block_id
+        {
+            if let Some(transactions) = self.block_transactions_cache.read().unwrap().get(&index) {
+                return Ok(transactions
+                    .iter()
+                    .filter(|txn| filter.matches(txn))
+                    .cloned()
+                    .collect());
+            }
+        }
+
+        let block = self
+            .block(&BlockRequest {
+                network_identifier,
+                block_identifier: Some(block_id),
+            })
+            .await?
+            .block
+            .ok_or_else(|| ApiError::BlockNotFound(None))?;
+        let filtered = block
+            .transactions
+            .iter()
+            .filter(|txn| filter.matches(txn))
+            .cloned()
+            .collect();
+        self.block_transactions_cache
+            .write()
+            .unwrap()
+            .insert(block.block_identifier.index, block.transactions);
+        Ok(filtered)
+    }
+
+    /// Sums how the account's balance of the currency changed after `from_block`, up to and
+    /// including `to_block_inclusive`
+    ///
+    /// The net change is the balance at `to_block_inclusive` minus the balance at `from_block`.
+    /// Fees are counted as sent, and failed transactions only count their fee
+    pub async fn account_balance_changes(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+        currency: &Currency,
+        from_block: u64,
+        to_block_inclusive: u64,
+    ) -> anyhow::Result<BalanceChangeSummary> {
+        if from_block > to_block_inclusive {
+            return Err(ApiError::InvalidInput(Some(format!(
+                "Block range {} to {} is empty",
+                from_block, to_block_inclusive
+            )))
+            .into());
+        }
+
+        let blocks: Vec<Block> = futures::stream::iter(from_block + 1..=to_block_inclusive)
+            .map(|block_index| {
+                let request = BlockRequest {
+                    network_identifier: network_identifier.clone(),
+                    block_identifier: Some(PartialBlockIdentifier::block_index(block_index)),
+                };
+                async move {
+                    self.block(&request).await?.block.ok_or_else(|| {
+                        anyhow::Error::from(ApiError::BlockNotFound(Some(format!(
+                            "Block {} not found",
+                            block_index
+                        ))))
+                    })
+                }
+            })
+            .buffered(BLOCK_FETCH_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<_>>()?;
+
+        summarize_balance_changes(
+            account,
+            currency,
+            blocks.iter().flat_map(|block| &block.transactions),
+        )
+    }
+
+    /// Retrieves the operations involving the account, in transactions at or after the version
+    ///
+    /// Each entry is a transaction's operations with the index of its block, in ascending order.
+    /// At most `limit` transactions are returned, so callers should checkpoint the last block
+    /// index and resume from there.  Requires a REST client, see
+    /// [`RosettaClient::with_rest_client`]
+    pub async fn account_operations_since_version(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+        since_version: u64,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(u64, Vec<Operation>)>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let response = self
+            .rest_client()?
+            .get_block_by_version(since_version, false)
+            .await
+            .map_err(ApiError::from)?;
+        check_chain_id(&network_identifier, response.state())?;
+        let start_index = response.into_inner().block_height.0;
+        let end_index = self.current_block_index(network_identifier.clone()).await?;
+
+        let all_transactions = TransactionFilter {
+            user: true,
+            metadata: true,
+            state_checkpoint: true,
+        };
+        let mut results = Vec::new();
+        for block_index in start_index..=end_index {
+            let transactions = self
+                .block_transactions_filtered(
+                    network_identifier.clone(),
+                    PartialBlockIdentifier::block_index(block_index),
+                    all_transactions,
+                )
+                .await?;
+            for transaction in transactions {
+                // The first block may have transactions from before the version
+                if transaction
+                    .metadata
+                    .map_or(false, |metadata| metadata.version.0 < since_version)
+                {
+                    continue;
+                }
+
+                let operations: Vec<Operation> = transaction
+                    .operations
+                    .into_iter()
+                    .filter(|operation| {
+                        operation
+                            .account
+                            .as_ref()
+                            .and_then(|account_id| account_id.account_address().ok())
+                            == Some(account)
+                    })
+                    .collect();
+                if !operations.is_empty() {
+                    results.push((block_index, operations));
+                    if results.len() >= limit {
+                        return Ok(results);
+                    }
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Retrieves the transaction and operation counts of a block, from the cache if possible
+    async fn block_counts(
+        &self,
+        network_identifier: NetworkIdentifier,
+        block_id: PartialBlockIdentifier,
+    ) -> anyhow::Result<(usize, usize)> {
+        // Blocks can only be looked up in the cache by index
+        if let PartialBlockIdentifier {
+            index: Some(index),
+            hash: None,
+        } = block_id
+        {
+            if let Some(counts) = self.block_counts_cache.read().unwrap().get(&index) {
+                return Ok(*counts);
+            }
+        }
+
+        let block = self
+            .block(&BlockRequest {
+                network_identifier,
+                block_identifier: Some(block_id),
+            })
+            .await?
+            .block
+            .ok_or_else(|| ApiError::BlockNotFound(None))?;
+        let counts = (
+            block.transactions.len(),
+            block
+                .transactions
+                .iter()
+                .map(|txn| txn.operations.len())
+                .sum(),
+        );
+        self.block_counts_cache
+            .write()
+            .unwrap()
+            .insert(block.block_identifier.index, counts);
+        Ok(counts)
+    }
+
+    /// Retrieves the amount of stake in the account's own stake pool
+    ///
+    /// This is the active stake, and stake that will become active in the next epoch.  Requires
+    /// a REST client, see [`RosettaClient::with_rest_client`]
+    pub async fn get_validator_stake(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+    ) -> anyhow::Result<u64> {
+        /// Type for deserializing the stake pool
+        #[derive(Deserialize)]
+        struct Coin {
+            value: U64,
+        }
+        #[derive(Deserialize)]
+        struct StakePool {
+            active: Coin,
+            pending_active: Coin,
+        }
+
+        let stake_pool: StakePool = self
+            .get_resource_at_version(&network_identifier, account, "0x1::stake::StakePool", None)
+            .await?;
+        Ok(stake_pool
+            .active
+            .value
+            .0
+            .saturating_add(stake_pool.pending_active.value.0))
+    }
+
+    /// Retrieves when the lockup of the account's stake pool expires, in seconds since the epoch
+    ///
+    /// Unlocked stake can only be withdrawn after this time.  Requires a REST client, see
+    /// [`RosettaClient::with_rest_client`]
+    pub async fn get_lockup_expiration(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+    ) -> anyhow::Result<u64> {
+        /// Type for deserializing the stake pool
+        #[derive(Deserialize)]
+        struct StakePool {
+            locked_until_secs: U64,
+        }
+
+        let stake_pool: StakePool = self
+            .get_resource_at_version(&network_identifier, account, "0x1::stake::StakePool", None)
+            .await?;
+        Ok(stake_pool.locked_until_secs.0)
+    }
+
+    /// Retrieves the validator's configuration, and who operates and votes for it
+    ///
+    /// Requires a REST client, see [`RosettaClient::with_rest_client`]
+    pub async fn validator_config(
+        &self,
+        network_identifier: NetworkIdentifier,
+        validator: AccountAddress,
+    ) -> anyhow::Result<ValidatorConfig> {
+        /// Types for deserializing the validator config, and its stake pool
+        #[derive(Deserialize)]
+        struct OnChainValidatorConfig {
+            consensus_pubkey: HexEncodedBytes,
+            network_addresses: HexEncodedBytes,
+            fullnode_addresses: HexEncodedBytes,
+        }
+        #[derive(Deserialize)]
+        struct StakePool {
+            operator_address: Address,
+            delegated_voter: Address,
+        }
+
+        let validator_config: OnChainValidatorConfig = self
+            .get_resource_at_version(
+                &network_identifier,
+                validator,
+                "0x1::stake::ValidatorConfig",
+                None,
+            )
+            .await?;
+        let stake_pool: StakePool = self
+            .get_resource_at_version(
+                &network_identifier,
+                validator,
+                "0x1::stake::StakePool",
+                None,
+            )
+            .await?;
+
+        Ok(ValidatorConfig {
+            consensus_pubkey: validator_config.consensus_pubkey.0,
+            network_addresses: validator_config.network_addresses.0,
+            fullnode_addresses: validator_config.fullnode_addresses.0,
+            operator_address: *stake_pool.operator_address.inner(),
+            voter_address: *stake_pool.delegated_voter.inner(),
+        })
+    }
+
+    /// Retrieves every validator in the active validator set, in the order of the set
+    ///
+    /// Stake pools are fetched concurrently, up to 50 at a time.
+    /// Requires a REST client, see [`RosettaClient::with_rest_client`]
+    pub async fn active_validators(
+        &self,
+        network_identifier: NetworkIdentifier,
+    ) -> anyhow::Result<Vec<ValidatorSummary>> {
+        /// Types for deserializing the validator set, and each validator's stake pool
+        #[derive(Deserialize)]
+        struct OnChainValidatorConfig {
+            consensus_pubkey: HexEncodedBytes,
+            network_addresses: HexEncodedBytes,
+            fullnode_addresses: HexEncodedBytes,
+        }
+        #[derive(Deserialize)]
+        struct ValidatorInfo {
+            addr: Address,
+            voting_power: U64,
+            config: OnChainValidatorConfig,
+        }
+        #[derive(Deserialize)]
+        struct ValidatorSet {
+            active_validators: Vec<ValidatorInfo>,
+        }
+        #[derive(Deserialize)]
+        struct Coin {
+            value: U64,
+        }
+        #[derive(Deserialize)]
+        struct StakePool {
+            active: Coin,
+            operator_address: Address,
+            delegated_voter: Address,
+        }
+
+        // Before genesis, or on networks without staking, there's no validator set
+        let network_identifier = &network_identifier;
+        let validator_set: ValidatorSet = self
+            .get_resource_at_version(
+                network_identifier,
+                AccountAddress::ONE,
+                "0x1::stake::ValidatorSet",
+                None,
+            )
+            .await
+            .map_err(|err| match err.downcast::<ApiError>() {
+                Ok(ApiError::AccountNotFound(_)) | Ok(ApiError::ResourceNotFound(_)) => {
+                    ApiError::ResourceNotFound(Some(
+                        "0x1::stake::ValidatorSet not found".to_string(),
+                    ))
+                    .into()
+                }
+                Ok(err) => err.into(),
+                Err(err) => err,
+            })?;
+
+        futures::stream::iter(validator_set.active_validators.into_iter().map(
+            |validator| async move {
+                let address = *validator.addr.inner();
+                let stake_pool: StakePool = self
+                    .get_resource_at_version(
+                        network_identifier,
+                        address,
+                        "0x1::stake::StakePool",
+                        None,
+                    )
+                    .await?;
+                Ok::<_, anyhow::Error>(ValidatorSummary {
+                    address,
+                    voting_power: validator.voting_power.0,
+                    active_stake: stake_pool.active.value.0,
+                    config: ValidatorConfig {
+                        consensus_pubkey: validator.config.consensus_pubkey.0,
+                        network_addresses: validator.config.network_addresses.0,
+                        fullnode_addresses: validator.config.fullnode_addresses.0,
+                        operator_address: *stake_pool.operator_address.inner(),
+                        voter_address: *stake_pool.delegated_voter.inner(),
+                    },
+                })
+            },
+        ))
+        .buffered(ACTIVE_VALIDATORS_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Looks up the resource account created by `creator` with the seed, `None` if it doesn't exist
+    pub async fn find_resource_account(
+        &self,
+        network_identifier: NetworkIdentifier,
+        creator: AccountAddress,
+        seed: &[u8],
+    ) -> anyhow::Result<Option<AccountIdentifier>> {
+        let address = derive_resource_account_address(creator, seed);
+        if self.account_exists(network_identifier, address).await? {
+            Ok(Some(address.into()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Checks whether the account exists, based on whether its balance can be found
+    async fn account_exists(
+        &self,
+        network_identifier: NetworkIdentifier,
+        address: AccountAddress,
+    ) -> anyhow::Result<bool> {
+        match self
+            .account_balance(&AccountBalanceRequest {
+                network_identifier,
+                account_identifier: address.into(),
+                block_identifier: None,
+                currencies: None,
+            })
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(err)
+                if err.downcast_ref::<Error>().map(|error| error.code)
+                    == Some(ApiError::AccountNotFound(None).code()) =>
+            {
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Whether the account has to register a `CoinStore` for APT before it can receive it
+    ///
+    /// Accounts that don't exist have nothing registered.  Requires a REST client, see
+    /// [`RosettaClient::with_rest_client`]
+    pub async fn needs_coin_registration(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+    ) -> anyhow::Result<bool> {
+        Ok(self
+            .get_optional_resource(
+                &network_identifier,
+                account,
+                "0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>",
+            )
+            .await?
+            .is_none())
+    }
+
+    /// Classifies the account at the address, based on the resources it holds
+    ///
+    /// Requires a REST client, see [`RosettaClient::with_rest_client`]
+    pub async fn account_type(
+        &self,
+        network_identifier: NetworkIdentifier,
+        address: AccountAddress,
+    ) -> anyhow::Result<AccountType> {
+        /// Type for deserializing the account
+        #[derive(Deserialize)]
+        struct Account {
+            authentication_key: HexEncodedBytes,
+        }
+
+        if is_treasury_or_system_address(address) {
+            return Ok(AccountType::System);
+        }
+
+        // Objects don't have an account resource
+        if self
+            .get_optional_resource(&network_identifier, address, "0x1::object::ObjectCore")
+            .await?
+            .is_some()
+        {
+            return Ok(AccountType::Object);
+        }
+
+        let account: Account = match self
+            .get_optional_resource(&network_identifier, address, "0x1::account::Account")
+            .await?
+        {
+            Some(account) => serde_json::from_value(account)
+                .map_err(|_| ApiError::deserialization_failed("Account"))?,
+            None => return Ok(AccountType::Unknown),
+        };
+
+        if self
+            .get_optional_resource(
+                &network_identifier,
+                address,
+                "0x1::multisig_account::MultisigAccount",
+            )
+            .await?
+            .is_some()
+        {
+            Ok(AccountType::MultisigAccount)
+        } else if account.authentication_key.0.iter().all(|byte| *byte == 0) {
+            // Resource accounts have their authentication key zeroed out, so nobody can sign for them
+            Ok(AccountType::ResourceAccount)
+        } else {
+            Ok(AccountType::Regular)
+        }
+    }
+
+    /// Retrieves a governance proposal, with its votes and status as of the latest version
+    ///
+    /// Requires a REST client, see [`RosettaClient::with_rest_client`]
+    pub async fn governance_proposal(
+        &self,
+        network_identifier: NetworkIdentifier,
+        proposal_id: u64,
+    ) -> anyhow::Result<GovernanceProposal> {
+        /// Types for deserializing the voting forum's proposals table
+        #[derive(Deserialize)]
+        struct Table {
+            handle: U128,
+        }
+        #[derive(Deserialize)]
+        struct VotingForum {
+            proposals: Table,
+        }
+        #[derive(Deserialize)]
+        struct MoveOption<T> {
+            vec: Vec<T>,
+        }
+        #[derive(Deserialize)]
+        struct Element {
+            key: String,
+            value: HexEncodedBytes,
+        }
+        #[derive(Deserialize)]
+        struct SimpleMap {
+            data: Vec<Element>,
+        }
+        #[derive(Deserialize)]
+        struct Proposal {
+            proposer: AccountAddress,
+            metadata: SimpleMap,
+            execution_hash: HexEncodedBytes,
+            min_vote_threshold: U128,
+            expiration_secs: U64,
+            early_resolution_vote_threshold: MoveOption<U128>,
+            yes_votes: U128,
+            no_votes: U128,
+            is_resolved: bool,
+        }
+
+        const PROPOSAL_TYPE: &str = "0x1::governance_proposal::GovernanceProposal";
+
+        let voting_forum: VotingForum = self
+            .get_resource_at_version(
+                &network_identifier,
+                AccountAddress::ONE,
+                &format!("0x1::voting::VotingForum<{}>", PROPOSAL_TYPE),
+                None,
+            )
+            .await?;
+
+        let response = self
+            .rest_client()?
+            .get_table_item(
+                voting_forum.proposals.handle.0,
+                "u64",
+                &format!("0x1::voting::Proposal<{}>", PROPOSAL_TYPE),
+                U64(proposal_id),
+            )
+            .await
+            .map_err(|err| match ApiError::from(err) {
+                ApiError::TableItemNotFound(_) => ApiError::TableItemNotFound(Some(format!(
+                    "Governance proposal {} not found",
+                    proposal_id
+                ))),
+                err => err,
+            })?;
+        check_chain_id(&network_identifier, response.state())?;
+        let now_secs = response.state().timestamp_usecs / 1_000_000;
+        let proposal = serde_json::from_value::<Proposal>(response.into_inner())
+            .map_err(|_| ApiError::deserialization_failed("Proposal"))?;
+
+        let metadata = |key: &str| {
+            proposal
+                .metadata
+                .data
+                .iter()
+                .find(|element| element.key == key)
+                .map(|element| String::from_utf8_lossy(&element.value.0).to_string())
+                .unwrap_or_default()
+        };
+
+        // Voting closes at expiration, or early once either side passes the early threshold
+        let yes_votes = proposal.yes_votes.0;
+        let no_votes = proposal.no_votes.0;
+        let resolvable_early = proposal
+            .early_resolution_vote_threshold
+            .vec
+            .first()
+            .map_or(false, |threshold| {
+                yes_votes >= threshold.0 || no_votes >= threshold.0
+            });
+        let status = if proposal.is_resolved {
+            ProposalStatus::Resolved
+        } else if now_secs <= proposal.expiration_secs.0 && !resolvable_early {
+            ProposalStatus::Pending
+        } else if yes_votes > no_votes
+            && yes_votes.saturating_add(no_votes) >= proposal.min_vote_threshold.0
+        {
+            ProposalStatus::Succeeded
+        } else {
+            ProposalStatus::Failed
+        };
+
+        Ok(GovernanceProposal {
+            id: proposal_id,
+            proposer: proposal.proposer,
+            title: metadata("metadata_location"),
+            description_hash: metadata("metadata_hash"),
+            yes_votes,
+            no_votes,
+            expiration_secs: proposal.expiration_secs.0,
+            execution_hash: Some(proposal.execution_hash.0)
+                .filter(|execution_hash| !execution_hash.is_empty())
+                .map(hex::encode),
+            status,
+        })
+    }
+
+    /// Retrieves an item from a Move table, at the latest version if no version is given
+    ///
+    /// Items at a past version can't change, so they're cached.  Requires a REST client, see
+    /// [`RosettaClient::with_rest_client`]
+    pub async fn get_table_item(
+        &self,
+        table_handle: &str,
+        key_type: &str,
+        value_type: &str,
+        key: serde_json::Value,
+        ledger_version: Option<u64>,
+    ) -> anyhow::Result<serde_json::Value> {
+        let cache_key = ledger_version.map(|version| {
+            (
+                table_handle.to_string(),
+                key_type.to_string(),
+                value_type.to_string(),
+                key.to_string(),
+                version,
+            )
+        });
+        if let Some(item) = cache_key
+            .as_ref()
+            .and_then(|cache_key| self.table_item_cache.get(cache_key))
+        {
+            return Ok(item);
+        }
+
+        let item = self
+            .rest_client()?
+            .get_table_item_at_version(table_handle, key_type, value_type, key, ledger_version)
+            .await
+            .map_err(ApiError::from)?
+            .into_inner();
+
+        if let Some(cache_key) = cache_key {
+            self.table_item_cache.insert(cache_key, item.clone());
+        }
+        Ok(item)
+    }
+
+    /// Retrieves a module's ABI and bytecode
+    ///
+    /// The bytecode is always fetched, as it's the only way to tell if the module was upgraded,
+    /// but the ABI is only parsed again if the bytecode changed.  Requires a REST client, see
+    /// [`RosettaClient::with_rest_client`]
+    pub async fn account_module(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+        module_name: &str,
+    ) -> anyhow::Result<ModuleMetadata> {
+        let (bytecode, state) = self
+            .rest_client()?
+            .get_account_module_bcs(account, module_name)
+            .await
+            .map_err(ApiError::from)?
+            .into_parts();
+        check_chain_id(&network_identifier, &state)?;
+        let bytecode_hex = hex::encode(&bytecode);
+
+        let key = (account, module_name.to_string());
+        if let Some((version, metadata)) = self.module_cache.write().unwrap().get_mut(&key) {
+            if metadata.bytecode_hex == bytecode_hex {
+                *version = std::cmp::max(*version, state.version);
+                return Ok(metadata.clone());
+            }
+        }
+
+        let abi = MoveModuleBytecode::new(bytecode.to_vec())
+            .try_parse_abi()?
+            .abi
+            .ok_or_else(|| ApiError::deserialization_failed("MoveModule"))?;
+        let metadata = ModuleMetadata {
+            exposed_functions: abi
+                .exposed_functions
+                .iter()
+                .map(|function| function.name.to_string())
+                .collect(),
+            abi: serde_json::to_value(&abi)?,
+            bytecode_hex,
+        };
+
+        // A different bytecode means the module was upgraded, so the old entry is replaced
+        self.module_cache
+            .write()
+            .unwrap()
+            .insert(key, (state.version, metadata.clone()));
+        Ok(metadata)
+    }
+
+    /// Retrieves an event by its sequence number in an account's event handle
+    ///
+    /// The handle is the resource and its field e.g.
+    /// `0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>/deposit_events`.  Requires a REST
+    /// client, see [`RosettaClient::with_rest_client`]
+    pub async fn event_at_version(
+        &self,
+        network_identifier: NetworkIdentifier,
+        account: AccountAddress,
+        event_handle_key: &str,
+        sequence_number: u64,
+    ) -> anyhow::Result<RosettaEvent> {
+        const ENCODE_CHARS: &AsciiSet = &CONTROLS.add(b'<').add(b'>');
+
+        let (struct_tag, field_name) = event_handle_key.rsplit_once('/').ok_or_else(|| {
+            anyhow!(
+                "Event handle {} must be of the form <struct_tag>/<field_name>",
+                event_handle_key
+            )
+        })?;
+
+        // Check the handle exists, so a missing handle isn't mistaken for a missing event
+        let is_cached = self
+            .event_handle_cache
+            .read()
+            .unwrap()
+            .get(&account)
+            .map_or(false, |handles| handles.contains(event_handle_key));
+        if !is_cached {
+            let resource: serde_json::Value = self
+                .get_resource_at_version(&network_identifier, account, struct_tag, None)
+                .await?;
+            if resource.get(field_name).is_none() {
+                return Err(ApiError::ResourceNotFound(Some(format!(
+                    "Event handle {} not found at {}",
+                    event_handle_key, account
+                )))
+                .into());
+            }
+            self.event_handle_cache
+                .write()
+                .unwrap()
+                .entry(account)
+                .or_default()
+                .insert(event_handle_key.to_string());
+        }
+
+        let response = self
+            .rest_client()?
+            .get_account_events(
+                account,
+                &utf8_percent_encode(struct_tag, ENCODE_CHARS).to_string(),
+                field_name,
+                Some(sequence_number),
+                Some(1),
+            )
+            .await
+            .map_err(ApiError::from)?;
+        check_chain_id(&network_identifier, response.state())?;
+        let event = response.into_inner().into_iter().next().ok_or_else(|| {
+            ApiError::ResourceNotFound(Some(format!(
+                "Event {} not found in {} at {}",
+                sequence_number, event_handle_key, account
+            )))
+        })?;
+
+        Ok(RosettaEvent {
+            version: event.version.0,
+            sequence_number: event.sequence_number.0,
+            event_type: event.typ.to_string(),
+            data: event.data,
+        })
+    }
+
+    /// Streams the events emitted in a block, in pages of `page_size` events
+    ///
+    /// The block's transactions are fetched a page at a time, rather than all at once, so large
+    /// blocks don't have to fit in memory.  Requires a REST client, see
+    /// [`RosettaClient::with_rest_client`]
+    pub fn block_events_paginated(
+        &self,
+        network_identifier: NetworkIdentifier,
+        block_id: PartialBlockIdentifier,
+        page_size: usize,
+    ) -> impl Stream<Item = anyhow::Result<Vec<RosettaEvent>>> + '_ {
+        let block_range = async move {
+            if page_size == 0 {
+                return Err(ApiError::InvalidInput(Some(
+                    "Page size must be greater than 0".to_string(),
+                ))
+                .into());
+            }
+
+            let rest_client = self.rest_client()?;
+            let block_index = match block_id {
+                PartialBlockIdentifier {
+                    index: Some(index),
+                    hash: None,
+                } => index,
+                block_identifier => {
+                    // Let the server resolve hashes and the latest block to an index
+                    self.block(&BlockRequest {
+                        network_identifier: network_identifier.clone(),
+                        block_identifier: Some(block_identifier),
+                    })
+                    .await?
+                    .block
+                    .ok_or_else(|| ApiError::BlockNotFound(None))?
+                    .block_identifier
+                    .index
+                }
+            };
+            let response = rest_client
+                .get_block_by_height(block_index, false)
+                .await
+                .map_err(ApiError::from)?;
+            check_chain_id(&network_identifier, response.state())?;
+            let block = response.into_inner();
+            Ok::<_, anyhow::Error>((rest_client, block.first_version.0, block.last_version.0))
+        };
+
+        futures::stream::once(block_range)
+            .map(move |block_range| match block_range {
+                Ok((rest_client, first_version, last_version)) => paginate_events(
+                    first_version,
+                    last_version,
+                    page_size,
+                    move |start, limit| async move {
+                        let transactions = rest_client
+                            .get_transactions(Some(start), Some(limit))
+                            .await
+                            .map_err(ApiError::from)?
+                            .into_inner();
+                        Ok::<Vec<Vec<RosettaEvent>>, anyhow::Error>(
+                            transactions.into_iter().map(transaction_events).collect(),
+                        )
+                    },
+                )
+                .left_stream(),
+                Err(err) => futures::stream::once(async move { Err(err) }).right_stream(),
+            })
+            .flatten()
+    }
+
+    /// Checks that the blocks from `start` up to `end_exclusive` link to each other by hash
+    ///
+    /// Up to `concurrency` blocks are fetched at a time.  Every broken link is reported, rather
+    /// than stopping at the first, so an empty list means the range is consistent
+    pub async fn validate_block_chain(
+        &self,
+        network_identifier: NetworkIdentifier,
+        start: u64,
+        end_exclusive: u64,
+        concurrency: usize,
+    ) -> anyhow::Result<Vec<ChainError>> {
+        let blocks: Vec<Block> = futures::stream::iter(start..end_exclusive)
+            .map(|block_index| {
+                let request = BlockRequest {
+                    network_identifier: network_identifier.clone(),
+                    block_identifier: Some(PartialBlockIdentifier::block_index(block_index)),
+                };
+                async move {
+                    self.block(&request).await?.block.ok_or_else(|| {
+                        anyhow::Error::from(ApiError::BlockNotFound(Some(format!(
+                            "Block {} not found",
+                            block_index
+                        ))))
+                    })
+                }
+            })
+            .buffered(concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(check_block_linkage(&blocks))
+    }
+
+    /// Converts a block to the last ledger version in it, `None` means the latest version
+    async fn block_version(
+        &self,
+        network_identifier: NetworkIdentifier,
+        block: Option<PartialBlockIdentifier>,
+    ) -> anyhow::Result<Option<u64>> {
+        let block_index = match block {
+            Some(PartialBlockIdentifier {
+                index: Some(index),
+                hash: None,
+            }) => index,
+            Some(block_identifier) => {
+                // Let the server resolve hashes to an index
+                self.block(&BlockRequest {
+                    network_identifier,
+                    block_identifier: Some(block_identifier),
+                })
+                .await?
+                .block
+                .ok_or_else(|| ApiError::BlockNotFound(None))?
+                .block_identifier
+                .index
+            }
+            None => return Ok(None),
+        };
+
+        let block = self
+            .rest_client()?
+            .get_block_by_height(block_index, false)
+            .await
+            .map_err(ApiError::from)?;
+        Ok(Some(block.into_inner().last_version.0))
+    }
+
+    /// Retrieves a resource from the REST API, erroring if it doesn't exist
+    ///
+    /// The REST client must be connected to the same chain as the request
+    async fn get_resource_at_version<T: DeserializeOwned>(
+        &self,
+        network_identifier: &NetworkIdentifier,
+        address: AccountAddress,
+        resource_type: &str,
+        version: Option<u64>,
+    ) -> anyhow::Result<T> {
+        let response = get_resource(self.rest_client()?, address, resource_type, version).await?;
+        check_chain_id(network_identifier, response.state())?;
+        Ok(response.into_inner())
+    }
+
+    /// Retrieves a resource at the latest version, or `None` if it or the account doesn't exist
+    async fn get_optional_resource(
+        &self,
+        network_identifier: &NetworkIdentifier,
+        address: AccountAddress,
+        resource_type: &str,
+    ) -> anyhow::Result<Option<serde_json::Value>> {
+        match self
+            .get_resource_at_version(network_identifier, address, resource_type, None)
+            .await
+        {
+            Ok(resource) => Ok(Some(resource)),
+            Err(err) => match err.downcast_ref::<ApiError>() {
+                Some(ApiError::ResourceNotFound(_)) | Some(ApiError::AccountNotFound(_)) => {
+                    Ok(None)
+                }
+                _ => Err(err),
+            },
+        }
+    }
+
+    /// Submits a signed transaction, and waits for it to be committed in a block
+    ///
+    /// Returns the block the transaction was committed in
+    pub async fn submit_and_wait(
+        &self,
+        network_identifier: NetworkIdentifier,
+        signed_transaction: String,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> anyhow::Result<(TransactionIdentifier, BlockResponse)> {
+        // Blocks before submission can't contain the transaction, so skip them
+        let start_block_index = self.current_block_index(network_identifier.clone()).await?;
+        let transaction_identifier = self
+            .submit_transaction(network_identifier.clone(), signed_transaction)
+            .await?;
+        let block = self
+            .wait_for_transaction(
+                network_identifier,
+                &transaction_identifier,
+                start_block_index,
+                poll_interval,
+                timeout,
+            )
+            .await?;
+
+        Ok((transaction_identifier, block))
+    }
+
+    /// Scans blocks from `start_block_index` until the transaction shows up in one of them
+    pub async fn wait_for_transaction(
+        &self,
+        network_identifier: NetworkIdentifier,
+        transaction_identifier: &TransactionIdentifier,
+        start_block_index: u64,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> anyhow::Result<BlockResponse> {
+        let start = Instant::now();
+        let mut next_block_index = start_block_index;
+        loop {
+            let current_block_index = self.current_block_index(network_identifier.clone()).await?;
+
+            // Check every block that's been committed since the last poll
+            while next_block_index <= current_block_index {
+                let response = self
+                    .block(&BlockRequest {
+                        network_identifier: network_identifier.clone(),
+                        block_identifier: Some(PartialBlockIdentifier::block_index(
+                            next_block_index,
+                        )),
+                    })
+                    .await?;
+                if let Some(ref block) = response.block {
+                    if block
+                        .transactions
+                        .iter()
+                        .any(|txn| &txn.transaction_identifier == transaction_identifier)
+                    {
+                        return Ok(response);
+                    }
+                }
+                next_block_index += 1;
+            }
+
+            if start.elapsed() >= timeout {
+                return Err(WaitForTransactionError::Timeout(timeout).into());
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Waits until the server has indexed the target block
+    ///
+    /// Polling backs off exponentially with jitter from `poll_interval`, up to 32 times
+    /// `poll_interval` or a minute, whichever is lower
+    pub async fn poll_until_indexed(
+        &self,
+        network_identifier: NetworkIdentifier,
+        target_block_index: u64,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        const MAX_BACKOFF: Duration = Duration::from_secs(60);
+        let max_backoff = std::cmp::min(poll_interval.saturating_mul(32), MAX_BACKOFF);
+
+        let start = Instant::now();
+        let mut backoff = poll_interval;
+        loop {
+            if self.current_block_index(network_identifier.clone()).await? >= target_block_index {
+                return Ok(());
+            }
+
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Err(WaitForTransactionError::Timeout(timeout).into());
+            }
+
+            // Jitter keeps many waiting clients from polling in lockstep
+            let jitter = backoff.mul_f64(rand::thread_rng().gen_range(0.0, 0.5));
+            let sleep = std::cmp::min(backoff + jitter, max_backoff);
+            tokio::time::sleep(std::cmp::min(sleep, timeout - elapsed)).await;
+            backoff = std::cmp::min(backoff.saturating_mul(2), max_backoff);
+        }
+    }
+
+    /// Retrieves a block, retrying while it isn't available yet
+    ///
+    /// Replicas can lag behind the node that reported a block, so a missing block is retried with
+    /// exponential back-off until `max_wait` has passed.  Pruned blocks will never show up, so
+    /// they are returned as an error straight away
+    pub async fn block_with_retries(
+        &self,
+        network_identifier: NetworkIdentifier,
+        block_id: PartialBlockIdentifier,
+        max_wait: Duration,
+    ) -> anyhow::Result<BlockResponse> {
+        const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+        const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+        let request = BlockRequest {
+            network_identifier,
+            block_identifier: Some(block_id),
+        };
+        let start = Instant::now();
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let err = match self.block(&request).await {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            // Errors from the server come back as Rosetta errors, so only the code is left
+            let not_available_yet = err.downcast_ref::<Error>().map_or(false, |error| {
+                error.code == ApiError::BlockNotFound(None).code()
+                    || error.code == ApiError::LedgerVersionTooHigh(None).code()
+            });
+            let elapsed = start.elapsed();
+            if !not_available_yet || elapsed >= max_wait {
+                return Err(err);
+            }
+
+            tokio::time::sleep(std::cmp::min(backoff, max_wait - elapsed)).await;
+            backoff = std::cmp::min(backoff.saturating_mul(2), MAX_BACKOFF);
+        }
+    }
+
+    /// Calls the server, retrying retriable errors if the client has a [`RetryConfig`]
+    async fn make_call<'a, I: Serialize + Debug, O: DeserializeOwned>(
+        &'a self,
+        path: &'static str,
+        request: &'a I,
+    ) -> anyhow::Result<O> {
+        let start = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let err = match self.make_call_once(path, request).await {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            // Only errors from the server say whether they're retriable
+            let retriable = err
+                .downcast_ref::<Error>()
+                .map_or(false, |error| error.retriable);
+            let retry_config = match self.retry_config {
+                Some(retry_config) if retriable && attempt < retry_config.max_attempts => {
+                    retry_config
+                }
+                _ => return Err(err),
+            };
+            let elapsed = start.elapsed();
+            if elapsed >= retry_config.max_total_delay {
+                return Err(err);
+            }
+
+            // Jitter keeps many retrying clients from calling in lockstep
+            let delay = retry_config.delay(attempt);
+            let jitter = delay.mul_f64(rand::thread_rng().gen_range(0.0, 0.5));
+            tokio::time::sleep(std::cmp::min(
+                delay + jitter,
+                retry_config.max_total_delay - elapsed,
+            ))
+            .await;
+            attempt += 1;
+        }
+    }
+
+    async fn make_call_once<'a, I: Serialize + Debug, O: DeserializeOwned>(
+        &'a self,
+        path: &'static str,
+        request: &'a I,
+    ) -> anyhow::Result<O> {
+        let response = self
+            .inner
+            .post(self.address.join(path)?)
+            .header(CONTENT_TYPE, JSON)
+            .body(serde_json::to_string(request)?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let retry_after_secs = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|retry_after| retry_after.to_str().ok())
+                .and_then(|retry_after| retry_after.parse::<u64>().ok());
+            let body = response.bytes().await?;
+            let error = match ApiError::from_response(status, &content_type, body) {
+                // Limits are per endpoint, which only the caller knows
+                ApiError::TooManyRequests {
+                    retry_after_secs: details_retry_after_secs,
+                    limit,
+                    window_secs,
+                    ..
+                } => ApiError::TooManyRequests {
+                    endpoint: path,
+                    retry_after_secs: retry_after_secs.or(details_retry_after_secs),
+                    limit,
+                    window_secs,
+                },
+                _ if status == StatusCode::TOO_MANY_REQUESTS => ApiError::TooManyRequests {
+                    endpoint: path,
+                    retry_after_secs,
+                    limit: None,
+                    window_secs: None,
+                },
+                error => error,
+            };
+            return Err(error.into_error().into());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Estimates the fee for a set of operations, without building the transaction
+    ///
+    /// The server derives the gas price and max gas from the fee policy, so pass the same policy
+    /// the transaction will be submitted with e.g. [`MaxFeePolicy::default`] for
+    /// [`ConstructionFlowBuilder`]
+    pub async fn fee_estimate_for_operations(
+        &self,
+        network_identifier: NetworkIdentifier,
+        operations: &[Operation],
+        private_key: &Ed25519PrivateKey,
+        fee_policy: MaxFeePolicy,
+    ) -> anyhow::Result<FeeEstimate> {
+        const OCTAS_PER_APT: f64 = 100_000_000.0;
+
+        let sender = self
+            .get_account_address(network_identifier.clone(), private_key.into())
+            .await?;
+        let mut keys = HashMap::new();
+        keys.insert(sender, private_key.into());
+
+        // Expiration doesn't affect the fee, but it's required for the metadata
+        let expiry_time_secs =
+            (SystemTime::now().duration_since(UNIX_EPOCH)? + EXPIRY_WINDOW).as_secs();
+        let (response, _) = self
+            .metadata_for_ops(
+                network_identifier,
+                operations.to_vec(),
+                fee_policy.max_fee,
+                fee_policy.fee_multiplier,
+                expiry_time_secs,
+                None,
+                &keys,
+            )
+            .await?;
+
+        let gas_used_estimate = response.metadata.max_gas;
+        let gas_unit_price = response.metadata.gas_price_per_unit;
+        let total_fee_octas = match response
+            .suggested_fee
+            .as_ref()
+            .and_then(|fees| fees.iter().find(|fee| fee.currency == native_coin()))
+        {
+            Some(fee) => u64::from_str(&fee.value)?,
+            // Older servers don't suggest a fee, so compute it the same way
+            None => gas_used_estimate.saturating_mul(gas_unit_price),
+        };
+
+        Ok(FeeEstimate {
+            gas_used_estimate,
+            gas_unit_price,
+            total_fee_octas,
+            total_fee_apt: total_fee_octas as f64 / OCTAS_PER_APT,
+        })
+    }
+
+    /// Estimates the cost of the creator creating a new account
+    ///
+    /// A `create_account` transaction from the creator is simulated, so only their public key is
+    /// needed, but they must have an account with enough gas.  Results are cached for five
+    /// minutes.  Requires a REST client for the simulation, see
+    /// [`RosettaClient::with_rest_client`]
+    pub async fn estimate_account_creation_cost(
+        &self,
+        network_identifier: NetworkIdentifier,
+        creator_public_key: &Ed25519PublicKey,
+    ) -> anyhow::Result<AccountCreationCost> {
+        if let Some(cost) = self.account_creation_cost_cache.get(&network_identifier) {
+            return Ok(cost);
+        }
+
+        let rest_client = self.rest_client()?;
+        let public_key = encode_public_key(creator_public_key)?;
+        let creator = self
+            .derive_account(network_identifier.clone(), public_key.clone())
+            .await?
+            .account_address()?;
+        // The gas doesn't depend on which account is created, as long as it doesn't exist yet, so
+        // a random address is used
+        let new_account = AccountAddress::new(rand::thread_rng().gen());
+        let operations = vec![Operation::create_account(0, None, new_account, creator)];
+        let fee_policy = MaxFeePolicy::default();
+        let expiry_time_secs =
+            (SystemTime::now().duration_since(UNIX_EPOCH)? + EXPIRY_WINDOW).as_secs();
+
+        let (_, preprocess_response) = self
+            .preprocess_only(
+                network_identifier.clone(),
+                operations.clone(),
+                fee_policy.max_fee,
+                fee_policy.fee_multiplier as f64,
+                expiry_time_secs,
+                None,
+            )
+            .await?;
+        let options = preprocess_response
+            .options
+            .ok_or_else(|| anyhow!("No metadata options returned from preprocess response"))?;
+        let metadata = self
+            .metadata(&ConstructionMetadataRequest {
+                network_identifier: network_identifier.clone(),
+                options,
+                public_keys: vec![public_key.clone()],
+            })
+            .await?;
+        let gas_unit_price = metadata.metadata.gas_price_per_unit;
+        let payloads = self
+            .unsigned_transaction(
+                network_identifier.clone(),
+                operations,
+                metadata.metadata,
+                vec![public_key],
+            )
+            .await?;
+
+        // Simulations must not have a valid signature, so a dummy one is used
+        let raw_transaction: RawTransaction =
+            bcs::from_bytes(&hex::decode(&payloads.unsigned_transaction)?)?;
+        let simulated_transaction = SignedTransaction::new(
+            raw_transaction,
+            creator_public_key.clone(),
+            Ed25519Signature::dummy_signature(),
+        );
+        let response = rest_client
+            .simulate(&simulated_transaction)
+            .await
+            .map_err(ApiError::from)?;
+        check_chain_id(&network_identifier, response.state())?;
+        let simulated = response
+            .into_inner()
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No transaction was simulated"))?;
+        if !simulated.info.success {
+            return Err(anyhow!(
+                "Failed to simulate account creation: {}",
+                simulated.info.vm_status
+            ));
+        }
+
+        let gas_used = simulated.info.gas_used.0;
+        let cost = AccountCreationCost {
+            gas_used,
+            gas_unit_price,
+            total_octas: gas_used.saturating_mul(gas_unit_price),
+        };
+        self.account_creation_cost_cache
+            .insert(network_identifier, cost);
+        Ok(cost)
+    }
+
+    /// Attempts to cancel a pending transaction by replacing it
+    ///
+    /// Aptos doesn't support cancelling transactions, so this submits a zero value transfer to
+    /// the sender with the same sequence number and a higher gas price.  This is best effort
+    /// only, it will fail if the original transaction has already been committed, and it isn't
+    /// guaranteed to replace the original transaction in mempool.
+    pub async fn cancel_transaction(
+        &self,
+        network_identifier: &NetworkIdentifier,
+        private_key: &Ed25519PrivateKey,
+        sequence_number: u64,
+        replacement_gas_price: u64,
+        expiry_time_secs: u64,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        let sender = self
+            .get_account_address(network_identifier.clone(), private_key.into())
+            .await?;
+
+        // If the sequence number has already been used, there's nothing left to cancel
+        let current_sequence_number = self
+            .account_sequence_number(network_identifier.clone(), sender)
+            .await?;
+        if sequence_number < current_sequence_number {
+            return Err(ApiError::SequenceNumberTooOld(Some(format!(
+                "Sequence number {} has already been committed, current sequence number is {}",
+                sequence_number, current_sequence_number
+            )))
+            .into());
+        }
+
+        let mut keys = HashMap::new();
+        keys.insert(sender, private_key.into());
+
+        // A transfer of nothing to ourselves has no effect other than using the sequence number
+        let operations = vec![
+            Operation::withdraw(0, None, sender, native_coin(), 0),
+            Operation::deposit(1, None, sender, native_coin(), 0),
+        ];
+
+        self.submit_operations_with_gas_price(
+            network_identifier.clone(),
+            &keys,
+            operations,
+            expiry_time_secs,
+            Some(sequence_number),
+            u32::try_from(replacement_gas_price)?,
+        )
+        .await
+    }
+
+    /// Submits operations, retrying with a new expiration time if the transaction expires
+    ///
+    /// The sequence number is fetched on every attempt, in case other transactions were
+    /// committed in the meantime
+    pub async fn submit_with_retry_on_expiry(
+        &self,
+        network_identifier: NetworkIdentifier,
+        keys: &HashMap<AccountAddress, SigningKey<'_>>,
+        operations: Vec<Operation>,
+        max_retries: u32,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        let mut attempts = 0;
+        loop {
+            let expiry_time_secs =
+                (SystemTime::now().duration_since(UNIX_EPOCH)? + EXPIRY_WINDOW).as_secs();
+            attempts += 1;
+            let err = match self
+                .submit_operations(
+                    network_identifier.clone(),
+                    keys,
+                    operations.clone(),
+                    expiry_time_secs,
+                    None,
+                )
+                .await
+            {
+                Ok(transaction_identifier) => return Ok(transaction_identifier),
+                Err(err) => err,
+            };
+
+            // Only expiration can be fixed by trying again
+            let last_error = match expiry_error(&err) {
+                Some(last_error) => last_error,
+                None => return Err(err),
+            };
+            if attempts > max_retries {
+                return Err(ApiError::MaxRetriesExceeded {
+                    attempts,
+                    last_error: Box::new(last_error),
+                }
+                .into());
+            }
+        }
+    }
+
+    pub async fn create_account(
+        &self,
+        network_identifier: &NetworkIdentifier,
+        private_key: &Ed25519PrivateKey,
+        new_account: AccountAddress,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        let sender = self
+            .get_account_address(network_identifier.clone(), private_key.into())
+            .await?;
+        let mut keys = HashMap::new();
+        keys.insert(sender, private_key.into());
+
+        if self
+            .account_exists(network_identifier.clone(), new_account)
+            .await?
+        {
+            return Err(ApiError::AccountAlreadyExists(Some(format!(
+                "Account {} already exists",
+                new_account
+            )))
+            .into());
+        }
+
+        // A create account transaction is just a Create account operation
+        let operations = vec![Operation::create_account(0, None, new_account, sender)];
+
+        self.submit_operations(
+            network_identifier.clone(),
+            &keys,
+            operations,
+            expiry_time_secs,
+            sequence_number,
+        )
+        .await
+    }
+
+    pub async fn add_validator_stake(
+        &self,
+        network_identifier: &NetworkIdentifier,
+        private_key: &Ed25519PrivateKey,
+        stake_amount: u64,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        let owner = self
+            .get_account_address(network_identifier.clone(), private_key.into())
+            .await?;
+        let mut keys = HashMap::new();
+        keys.insert(owner, private_key.into());
+
+        // The server checks the minimum stake too, but checking here avoids building the transaction
+        if self.rest_client.is_some() {
+            let rest_client = self.rest_client()?;
+            check_minimum_stake(
+                get_pool_stake(rest_client, owner).await?,
+                stake_amount,
+                get_minimum_stake(rest_client).await?,
+            )?;
+        }
+
+        // Stake is added to the owner's own stake pool
+        let operations = vec![Operation::add_validator_stake(0, None, owner, stake_amount)];
+
+        self.submit_operations(
+            network_identifier.clone(),
+            &keys,
+            operations,
+            expiry_time_secs,
+            sequence_number,
+        )
+        .await
+    }
+
+    /// Unlocks active stake in the owner's stake pool
+    ///
+    /// The stake becomes withdrawable once the lockup expires, see
+    /// [`RosettaClient::get_lockup_expiration`]
+    pub async fn unlock_stake(
+        &self,
+        network_identifier: &NetworkIdentifier,
+        private_key: &Ed25519PrivateKey,
+        amount: u64,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        let owner = self
+            .get_account_address(network_identifier.clone(), private_key.into())
+            .await?;
+        let mut keys = HashMap::new();
+        keys.insert(owner, private_key.into());
+
+        let operations = vec![Operation::unlock_stake(0, None, owner, amount)];
+
+        self.submit_operations(
+            network_identifier.clone(),
+            &keys,
+            operations,
+            expiry_time_secs,
+            sequence_number,
+        )
+        .await
+    }
+
+    /// Withdraws all unlocked stake in the owner's stake pool back to the owner
+    pub async fn withdraw_inactive_stake(
+        &self,
+        network_identifier: &NetworkIdentifier,
+        private_key: &Ed25519PrivateKey,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        let owner = self
+            .get_account_address(network_identifier.clone(), private_key.into())
+            .await?;
+        let mut keys = HashMap::new();
+        keys.insert(owner, private_key.into());
+
+        let operations = vec![Operation::withdraw_inactive_stake(0, None, owner, None)];
+
+        self.submit_operations(
+            network_identifier.clone(),
+            &keys,
+            operations,
+            expiry_time_secs,
+            sequence_number,
+        )
+        .await
+    }
+
+    /// Rotates the account's authentication key from the current key to the new key
+    ///
+    /// Both keys sign the account's `0x1::account::RotationProofChallenge`, and the signatures
+    /// are passed along in the operation's metadata
+    pub async fn rotate_authentication_key(
+        &self,
+        network_identifier: &NetworkIdentifier,
+        current_private_key: &Ed25519PrivateKey,
+        new_private_key: &Ed25519PrivateKey,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        /// Challenge signed by both keys, must match the layout of the onchain struct
+        #[derive(Serialize)]
+        struct RotationProofChallenge {
+            account_address: AccountAddress,
+            module_name: String,
+            struct_name: String,
+            sequence_number: u64,
+            originator: AccountAddress,
+            current_auth_key: AccountAddress,
+            new_public_key: Vec<u8>,
+        }
+
+        let account = self
+            .get_account_address(network_identifier.clone(), current_private_key.into())
+            .await?;
+
+        // The challenge is bound to the sequence number the rotation will be submitted with
+        let sequence_number = if let Some(sequence_number) = sequence_number {
+            sequence_number
+        } else {
+            self.account_sequence_number(network_identifier.clone(), account)
+                .await?
+        };
+
+        let current_public_key = current_private_key.public_key();
+        let new_public_key = new_private_key.public_key();
+        let challenge = RotationProofChallenge {
+            account_address: AccountAddress::ONE,
+            module_name: "account".to_string(),
+            struct_name: "RotationProofChallenge".to_string(),
+            sequence_number,
+            originator: account,
+            current_auth_key: AuthenticationKey::ed25519(&current_public_key).derived_address(),
+            new_public_key: new_public_key.to_bytes().to_vec(),
+        };
+        let challenge = bcs::to_bytes(&challenge)?;
+
+        let rotate_key = RotateKey {
+            account,
+            current_public_key: current_public_key.to_bytes().to_vec(),
+            new_public_key: new_public_key.to_bytes().to_vec(),
+            current_signature: current_private_key
+                .sign_arbitrary_message(&challenge)
+                .to_bytes()
+                .to_vec(),
+            new_signature: new_private_key
+                .sign_arbitrary_message(&challenge)
+                .to_bytes()
+                .to_vec(),
+        };
+
+        let mut keys = HashMap::new();
+        keys.insert(account, current_private_key.into());
+        keys.insert(
+            AuthenticationKey::ed25519(&new_public_key).derived_address(),
+            new_private_key.into(),
+        );
+
+        let operations = vec![Operation::rotate_key(0, None, account, &rotate_key)];
+
+        self.submit_operations(
+            network_identifier.clone(),
+            &keys,
+            operations,
+            expiry_time_secs,
+            Some(sequence_number),
+        )
+        .await
+    }
+
+    pub async fn transfer<'a>(
+        &self,
+        network_identifier: &NetworkIdentifier,
+        private_key: impl Into<SigningKey<'a>>,
+        receiver: AccountAddress,
+        amount: u64,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        let private_key = private_key.into();
+        let sender = self
+            .get_account_address(network_identifier.clone(), private_key)
+            .await?;
+        let mut keys = HashMap::new();
+        keys.insert(sender, private_key);
+
+        // Sending to a receiver that can't take APT would abort onchain, and still charge gas
+        if self.rest_client.is_some()
+            && self
+                .needs_coin_registration(network_identifier.clone(), receiver)
+                .await?
+        {
+            let receiver_exists = self
+                .account_exists(network_identifier.clone(), receiver)
+                .await?;
+            return Err(ApiError::aptos_coin_not_registered(receiver, receiver_exists).into());
+        }
+
+        // A transfer operation is made up of a withdraw and a deposit
+        let operations = vec![
+            Operation::withdraw(0, None, sender, native_coin(), amount),
+            Operation::deposit(1, None, receiver, native_coin(), amount),
+        ];
+        validate_single_sender_operations(&operations)?;
+
+        self.submit_operations(
+            network_identifier.clone(),
+            &keys,
+            operations,
+            expiry_time_secs,
+            sequence_number,
+        )
+        .await
+    }
+
+    /// Retrieves the account address from the derivation path if there isn't an overriding account specified
+    async fn get_account_address(
+        &self,
+        network_identifier: NetworkIdentifier,
+        signing_key: SigningKey<'_>,
+    ) -> anyhow::Result<AccountAddress> {
+        match signing_key {
+            // The server can only derive single key accounts
+            SigningKey::MultiEd25519(key_set) => Ok(key_set.account_address()),
+            SigningKey::Ed25519(_) => Ok(self
+                .derive_account(network_identifier, signing_key.public_key()?)
+                .await?
+                .account_address()?),
+        }
+    }
+
+    /// Submits the operations to the blockchain
+    async fn submit_operations(
+        &self,
+        network_identifier: NetworkIdentifier,
+        keys: &HashMap<AccountAddress, SigningKey<'_>>,
+        operations: Vec<Operation>,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        self.submit_operations_with_gas_price(
+            network_identifier,
+            keys,
+            operations,
+            expiry_time_secs,
+            sequence_number,
+            1,
+        )
+        .await
+    }
+
+    /// Submits operations with a gas price, which Rosetta takes as the suggested fee multiplier
+    async fn submit_operations_with_gas_price(
+        &self,
+        network_identifier: NetworkIdentifier,
+        keys: &HashMap<AccountAddress, SigningKey<'_>>,
+        operations: Vec<Operation>,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+        fee_multiplier: u32,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        // Retrieve txn metadata
+        let (metadata, public_keys) = self
+            .metadata_for_ops(
+                network_identifier.clone(),
+                operations.clone(),
+                10000,
+                fee_multiplier,
+                expiry_time_secs,
+                sequence_number,
+                keys,
+            )
+            .await?;
+
+        // Build the transaction, sign it, and submit it
+        let response = self
             .unsigned_transaction(
                 network_identifier.clone(),
                 operations.clone(),
@@ -257,12 +3959,163 @@ impl RosettaClient {
             .await
     }
 
+    /// Builds and signs the operations, returning the transaction's hash without submitting it
+    ///
+    /// The hash is only valid as long as the transaction is submitted exactly as signed, so the
+    /// signed transaction is returned alongside it, hex encoded for `construction/submit`
+    pub async fn compute_transaction_hash_from_operations(
+        &self,
+        network_identifier: NetworkIdentifier,
+        private_key: &Ed25519PrivateKey,
+        operations: Vec<Operation>,
+        max_fee: u64,
+        fee_multiplier: u32,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+    ) -> anyhow::Result<(TransactionIdentifier, String)> {
+        let sender = self
+            .get_account_address(network_identifier.clone(), private_key.into())
+            .await?;
+        let mut keys = HashMap::new();
+        keys.insert(sender, private_key.into());
+
+        let (metadata, public_keys) = self
+            .metadata_for_ops(
+                network_identifier.clone(),
+                operations.clone(),
+                max_fee,
+                fee_multiplier,
+                expiry_time_secs,
+                sequence_number,
+                &keys,
+            )
+            .await?;
+        let response = self
+            .unsigned_transaction(
+                network_identifier.clone(),
+                operations.clone(),
+                metadata.metadata,
+                public_keys,
+            )
+            .await?;
+        let signed_transaction = self
+            .sign_transaction(network_identifier, &keys, response, operations)
+            .await?;
+
+        let signed_txn: SignedTransaction = bcs::from_bytes(&hex::decode(&signed_transaction)?)?;
+        let hash = Transaction::UserTransaction(signed_txn).hash();
+        Ok((
+            TransactionIdentifier {
+                hash: to_hex_lower(&hash),
+            },
+            signed_transaction,
+        ))
+    }
+
+    /// Runs the operations through the whole construction flow and simulates the result
+    ///
+    /// Nothing is submitted, so this is safe to run against live networks e.g. in CI.  Each stage
+    /// that passes is recorded in the result, and the first failure is kept as its error.
+    /// Requires a REST client for the simulation, see [`RosettaClient::with_rest_client`]
+    pub async fn simulation_test_submit(
+        &self,
+        network_identifier: NetworkIdentifier,
+        private_key: &Ed25519PrivateKey,
+        operations: Vec<Operation>,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+    ) -> anyhow::Result<SimulationTestResult> {
+        let rest_client = self.rest_client()?;
+        let sender = self
+            .get_account_address(network_identifier.clone(), private_key.into())
+            .await?;
+        let mut keys = HashMap::new();
+        keys.insert(sender, private_key.into());
+        let mut result = SimulationTestResult::default();
+
+        // Preprocess and metadata
+        let (metadata, public_keys) = match self
+            .metadata_for_ops(
+                network_identifier.clone(),
+                operations.clone(),
+                10000,
+                1,
+                expiry_time_secs,
+                sequence_number,
+                &keys,
+            )
+            .await
+        {
+            Ok(metadata) => metadata,
+            Err(err) => return Ok(result.failed("metadata", err)),
+        };
+        result.gas_unit_price = metadata.metadata.gas_price_per_unit;
+
+        // Payloads, and parsing the unsigned transaction
+        let unsigned_response = match self
+            .unsigned_transaction(
+                network_identifier.clone(),
+                operations.clone(),
+                metadata.metadata,
+                public_keys,
+            )
+            .await
+        {
+            Ok(unsigned_response) => unsigned_response,
+            Err(err) => return Ok(result.failed("parse (unsigned)", err)),
+        };
+        result.parse_valid = true;
+
+        // Combine, and parsing the signed transaction
+        let signed_transaction = match self
+            .sign_transaction(
+                network_identifier.clone(),
+                &keys,
+                unsigned_response,
+                operations,
+            )
+            .await
+        {
+            Ok(signed_transaction) => signed_transaction,
+            Err(err) => return Ok(result.failed("combine", err)),
+        };
+        result.combine_valid = true;
+
+        // Simulations must not have a valid signature, so the signature is swapped out
+        let signed_transaction: SignedTransaction =
+            bcs::from_bytes(&hex::decode(signed_transaction)?)?;
+        let simulated_transaction = SignedTransaction::new(
+            signed_transaction.into_raw_transaction(),
+            private_key.public_key(),
+            Ed25519Signature::dummy_signature(),
+        );
+        let simulated = match rest_client.simulate(&simulated_transaction).await {
+            Ok(response) => {
+                check_chain_id(&network_identifier, response.state())?;
+                response.into_inner()
+            }
+            Err(err) => return Ok(result.failed("simulate", ApiError::from(err))),
+        };
+        let simulated = match simulated.into_iter().next() {
+            Some(simulated) => simulated,
+            None => return Ok(result.failed("simulate", "no transaction was simulated")),
+        };
+
+        result.estimated_gas = simulated.info.gas_used.0;
+        result.would_succeed = simulated.info.success;
+        if !simulated.info.success {
+            result.error = Some(format!("simulate failed: {}", simulated.info.vm_status));
+        }
+        Ok(result)
+    }
+
     /// Derives an [`AccountAddress`] from the [`PublicKey`]
     async fn derive_account(
         &self,
         network_identifier: NetworkIdentifier,
         public_key: PublicKey,
     ) -> anyhow::Result<AccountIdentifier> {
+        validate_public_key(&public_key)?;
         if let ConstructionDeriveResponse {
             account_identifier: Some(account_id),
         } = self
@@ -278,25 +4131,25 @@ impl RosettaClient {
         }
     }
 
-    /// Retrieves the metadata for the set of operations
-    async fn metadata_for_ops(
+    /// Preprocesses the operations, returning the accounts that must sign alongside the response
+    ///
+    /// Useful for showing who needs to sign before any keys are available, as it doesn't
+    /// fetch the metadata
+    pub async fn preprocess_only(
         &self,
         network_identifier: NetworkIdentifier,
         operations: Vec<Operation>,
         max_fee: u64,
-        fee_multiplier: u32,
+        fee_multiplier: f64,
         expiry_time_secs: u64,
         sequence_number: Option<u64>,
-        keys: &HashMap<AccountAddress, &Ed25519PrivateKey>,
-    ) -> anyhow::Result<(ConstructionMetadataResponse, Vec<PublicKey>)> {
-        // Request the given operation with the given gas constraints
-        let amount = val_to_amount(max_fee, false);
+    ) -> anyhow::Result<(Vec<AccountIdentifier>, ConstructionPreprocessResponse)> {
         let preprocess_response = self
             .preprocess(&ConstructionPreprocessRequest {
-                network_identifier: network_identifier.clone(),
+                network_identifier,
                 operations,
-                max_fee: Some(vec![amount]),
-                suggested_fee_multiplier: Some(fee_multiplier as f64),
+                max_fee: Some(vec![format_amount(i128::from(max_fee), native_coin())]),
+                suggested_fee_multiplier: Some(fee_multiplier),
                 metadata: Some(PreprocessMetadata {
                     expiry_time_secs: Some(expiry_time_secs),
                     sequence_number,
@@ -304,29 +4157,137 @@ impl RosettaClient {
             })
             .await?;
 
+        if let Some(ref accounts) = preprocess_response.required_public_keys {
+            Ok((accounts.clone(), preprocess_response))
+        } else {
+            Err(anyhow!("No public keys found required for transaction"))
+        }
+    }
+
+    /// Starts building a transaction step by step, see [`ConstructionFlowBuilder`]
+    pub fn construction_flow_builder(
+        &self,
+        network_identifier: NetworkIdentifier,
+    ) -> ConstructionFlowBuilder {
+        ConstructionFlowBuilder {
+            client: self.clone(),
+            network_identifier,
+            operations: Vec::new(),
+            fee_policy: MaxFeePolicy::default(),
+            expiry_time_secs: None,
+            sequence_number: None,
+        }
+    }
+
+    /// Preprocesses many transactions concurrently, giving each the next sequence number
+    ///
+    /// The `i`th set of operations uses sequence number `seq_start + i`, and results are returned
+    /// in the same order as the operation sets.  At most [`PREPROCESS_BATCH_CONCURRENCY`] calls
+    /// are in flight at once
+    pub async fn preprocess_batch(
+        &self,
+        network_identifier: NetworkIdentifier,
+        operation_sets: Vec<Vec<Operation>>,
+        max_fee: u64,
+        fee_multiplier: f64,
+        expiry_time_secs: u64,
+        seq_start: u64,
+    ) -> Vec<anyhow::Result<ConstructionPreprocessResponse>> {
+        let requests = operation_sets
+            .into_iter()
+            .zip(seq_start..)
+            .map(
+                |(operations, sequence_number)| ConstructionPreprocessRequest {
+                    network_identifier: network_identifier.clone(),
+                    operations,
+                    max_fee: Some(vec![format_amount(i128::from(max_fee), native_coin())]),
+                    suggested_fee_multiplier: Some(fee_multiplier),
+                    metadata: Some(PreprocessMetadata {
+                        expiry_time_secs: Some(expiry_time_secs),
+                        sequence_number: Some(sequence_number),
+                    }),
+                },
+            )
+            .collect::<Vec<_>>();
+
+        futures::stream::iter(requests.iter().map(|request| self.preprocess(request)))
+            .buffered(PREPROCESS_BATCH_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Retrieves the metadata for the set of operations
+    async fn metadata_for_ops(
+        &self,
+        network_identifier: NetworkIdentifier,
+        operations: Vec<Operation>,
+        max_fee: u64,
+        fee_multiplier: u32,
+        expiry_time_secs: u64,
+        sequence_number: Option<u64>,
+        keys: &HashMap<AccountAddress, SigningKey<'_>>,
+    ) -> anyhow::Result<(ConstructionMetadataResponse, Vec<PublicKey>)> {
+        // Request the given operation with the given gas constraints
+        let (accounts, preprocess_response) = self
+            .preprocess_only(
+                network_identifier.clone(),
+                operations.clone(),
+                max_fee,
+                fee_multiplier as f64,
+                expiry_time_secs,
+                sequence_number,
+            )
+            .await?;
+
         // Process the required public keys
         let mut public_keys = Vec::new();
-        if let Some(accounts) = preprocess_response.required_public_keys {
-            for account in accounts {
-                if let Some(key) = keys.get(&account.account_address()?) {
-                    public_keys.push(key.public_key().try_into()?);
-                } else {
-                    return Err(anyhow!("No public key found for account"));
-                }
+        for account in accounts {
+            if let Some(key) = keys.get(&account.account_address()?) {
+                public_keys.push(key.public_key()?);
+            } else {
+                return Err(anyhow!("No public key found for account"));
             }
-        } else {
-            return Err(anyhow!("No public keys found required for transaction"));
-        };
+        }
 
         // Request the metadata
         if let Some(options) = preprocess_response.options {
-            self.metadata(&ConstructionMetadataRequest {
-                network_identifier,
+            let request = ConstructionMetadataRequest {
+                network_identifier: network_identifier.clone(),
                 options,
                 public_keys: public_keys.clone(),
-            })
-            .await
-            .map(|response| (response, public_keys))
+            };
+            let response = if let Some(ref metadata_cache) = self.metadata_cache {
+                let mut response = metadata_cache
+                    .get_or_fetch(
+                        &network_identifier,
+                        &operations,
+                        max_fee,
+                        fee_multiplier,
+                        sequence_number,
+                        || self.metadata(&request),
+                    )
+                    .await?;
+
+                // Only the gas fields are reused, the rest is specific to this transaction
+                response.metadata.expiry_time_secs = Some(expiry_time_secs);
+                response
+            } else {
+                self.metadata(&request).await?
+            };
+
+            validate_construction_metadata(&response)?;
+
+            // The transaction would be rejected, so there's no point building it
+            if let Some(min_gas_price_per_unit) = response.metadata.min_gas_price_per_unit {
+                if (fee_multiplier as u64) < min_gas_price_per_unit {
+                    return Err(ApiError::GasPriceUnderMinimum(Some(format!(
+                        "Gas price {} octas is below the minimum of {} octas",
+                        fee_multiplier, min_gas_price_per_unit
+                    )))
+                    .into());
+                }
+            }
+            Ok((response, public_keys))
         } else {
             Err(anyhow!(
                 "No metadata options returned from preprocess response"
@@ -378,12 +4339,13 @@ impl RosettaClient {
     async fn sign_transaction(
         &self,
         network_identifier: NetworkIdentifier,
-        keys: &HashMap<AccountAddress, &Ed25519PrivateKey>,
+        keys: &HashMap<AccountAddress, SigningKey<'_>>,
         unsigned_response: ConstructionPayloadsResponse,
         operations: Vec<Operation>,
     ) -> anyhow::Result<String> {
         let mut signatures = Vec::new();
         let mut signers: Vec<AccountIdentifier> = Vec::new();
+        let mut multi_ed25519_public_key = None;
 
         // Sign the unsigned transaction
         let unsigned_transaction: RawTransaction = bcs::from_bytes(&hex::decode(
@@ -392,6 +4354,7 @@ impl RosettaClient {
         let signing_message = hex::encode(unsigned_transaction.signing_message());
 
         // Sign the payload if it matches the unsigned transaction
+        let expected_signers = unsigned_response.payloads.len();
         for payload in unsigned_response.payloads.into_iter() {
             let account = payload
                 .account_identifier
@@ -403,25 +4366,153 @@ impl RosettaClient {
             signers.push(account.clone());
 
             assert_eq!(signing_message, payload.hex_bytes);
-            let txn_signature = private_key.sign(&unsigned_transaction);
-            signatures.push(Signature {
-                signing_payload: payload,
-                public_key: private_key.public_key().try_into()?,
-                signature_type: SignatureType::Ed25519,
-                hex_bytes: txn_signature.to_encoded_string()?,
-            });
+            // A key set signs with each of its keys, which the server combines with its public key
+            if let SigningKey::MultiEd25519(_) = private_key {
+                multi_ed25519_public_key = Some(private_key.public_key()?.hex_bytes);
+            }
+            signatures.extend(private_key.sign(&payload, &unsigned_transaction)?);
+        }
+
+        // Every required signer must have signed before combining, key sets sign more than once
+        let signed_accounts: HashSet<_> = signatures
+            .iter()
+            .filter_map(|signature| signature.signing_payload.account_identifier.as_ref())
+            .map(|account| account.address.as_str())
+            .collect();
+        if signed_accounts.len() != expected_signers {
+            return Err(ApiError::SignatureCountMismatch {
+                expected: expected_signers,
+                got: signed_accounts.len(),
+            }
+            .into());
+        }
+
+        check_conflicting_signers(&signatures)?;
+
+        // Build the signed transaction
+        let signed_response = self
+            .combine(&ConstructionCombineRequest {
+                network_identifier: network_identifier.clone(),
+                unsigned_transaction: unsigned_response.unsigned_transaction,
+                signatures,
+                multi_ed25519_public_key,
+            })
+            .await?;
+
+        // Verify transaction can be parsed properly
+        let response = self
+            .parse(&ConstructionParseRequest {
+                network_identifier,
+                signed: true,
+                transaction: signed_response.signed_transaction.clone(),
+            })
+            .await?;
+
+        // Signers must match exactly
+        if let Some(parsed_signers) = response.account_identifier_signers {
+            if signers != parsed_signers {
+                return Err(anyhow!(
+                    "Signers don't match Expected: {:?} Got: {:?}",
+                    signers,
+                    parsed_signers
+                ));
+            }
+        } else {
+            return Err(anyhow!("Signers were in the unsigned transaction!"));
+        }
+
+        // Operations must match exactly
+        if operations != response.operations {
+            Err(anyhow!(
+                "Operations were not parsed to be the same as input! Expected {:?} Got {:?}",
+                operations,
+                response.operations
+            ))
+        } else {
+            Ok(signed_response.signed_transaction)
+        }
+    }
+
+    /// Decodes an unsigned transaction locally, without calling the server
+    ///
+    /// Useful for figuring out why parsing doesn't match the operations a transaction was built from
+    #[cfg(feature = "debug")]
+    pub fn debug_unsigned_transaction(
+        unsigned_hex: &str,
+    ) -> anyhow::Result<UnsignedTransactionDebug> {
+        let raw_transaction: RawTransaction = decode_bcs(unsigned_hex, "UnsignedTransaction")?;
+        let signing_message_hex = hex::encode(raw_transaction.signing_message());
+        let expected_operations = crate::construction::parse_operations(
+            raw_transaction.sender(),
+            raw_transaction.clone().into_payload(),
+        )?;
+
+        Ok(UnsignedTransactionDebug {
+            raw_transaction,
+            signing_message_hex,
+            expected_operations,
+        })
+    }
+
+    /// Signs a single [`SigningPayload`] without combining it into a signed transaction
+    ///
+    /// This allows each party of a multi-party transaction to sign independently, and
+    /// hand their [`Signature`] to whoever calls [`RosettaClient::collect_multisig_signatures`]
+    pub fn sign_multisig_payload(
+        payload: &SigningPayload,
+        private_key: &Ed25519PrivateKey,
+    ) -> anyhow::Result<Signature> {
+        // The signing message is the RawTransaction's hash prefix followed by its BCS bytes
+        let signing_message = hex::decode(&payload.hex_bytes)?;
+        if signing_message.len() < HashValue::LENGTH {
+            return Err(anyhow!("Signing payload is too short to be a transaction"));
+        }
+        let unsigned_transaction: RawTransaction =
+            bcs::from_bytes(&signing_message[HashValue::LENGTH..])?;
+        if unsigned_transaction.signing_message() != signing_message {
+            return Err(anyhow!("Signing payload doesn't match a RawTransaction"));
         }
 
-        // Build the signed transaction
+        let txn_signature = private_key.sign(&unsigned_transaction);
+        Ok(Signature {
+            signing_payload: payload.clone(),
+            public_key: encode_public_key(&private_key.public_key())?,
+            signature_type: SignatureType::Ed25519,
+            hex_bytes: txn_signature.to_encoded_string()?,
+        })
+    }
+
+    /// Combines signatures gathered from [`RosettaClient::sign_multisig_payload`] with the
+    /// unsigned transaction
+    ///
+    /// Returns the signed transaction after verifying that it parses with the expected signers
+    pub async fn collect_multisig_signatures(
+        &self,
+        network_identifier: NetworkIdentifier,
+        unsigned_transaction: String,
+        signatures: Vec<Signature>,
+    ) -> anyhow::Result<String> {
+        let signers: Vec<AccountIdentifier> = signatures
+            .iter()
+            .map(|signature| {
+                signature
+                    .signing_payload
+                    .account_identifier
+                    .clone()
+                    .ok_or_else(|| anyhow!("Signing payload must have an account"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
         let signed_response = self
             .combine(&ConstructionCombineRequest {
                 network_identifier: network_identifier.clone(),
-                unsigned_transaction: unsigned_response.unsigned_transaction,
+                unsigned_transaction,
                 signatures,
+                multi_ed25519_public_key: None,
             })
             .await?;
 
-        // Verify transaction can be parsed properly
+        // Verify the signed transaction has exactly the signers provided
         let response = self
             .parse(&ConstructionParseRequest {
                 network_identifier,
@@ -429,30 +4520,77 @@ impl RosettaClient {
                 transaction: signed_response.signed_transaction.clone(),
             })
             .await?;
-
-        // Signers must match exactly
-        if let Some(parsed_signers) = response.account_identifier_signers {
-            if signers != parsed_signers {
-                return Err(anyhow!(
-                    "Signers don't match Expected: {:?} Got: {:?}",
-                    signers,
-                    parsed_signers
-                ));
+        match response.account_identifier_signers {
+            Some(parsed_signers) if parsed_signers == signers => {
+                Ok(signed_response.signed_transaction)
             }
-        } else {
-            return Err(anyhow!("Signers were in the unsigned transaction!"));
+            parsed_signers => Err(anyhow!(
+                "Signers don't match Expected: {:?} Got: {:?}",
+                signers,
+                parsed_signers
+            )),
         }
+    }
 
-        // Operations must match exactly
-        if operations != response.operations {
-            Err(anyhow!(
-                "Operations were not parsed to be the same as input! Expected {:?} Got {:?}",
-                operations,
-                response.operations
-            ))
-        } else {
-            Ok(signed_response.signed_transaction)
+    /// Submits a transaction that was signed outside of the client e.g. by a hardware wallet
+    ///
+    /// The transaction is checked to be a hex encoded, BCS encoded [`SignedTransaction`] before
+    /// it's sent to the server
+    pub async fn send_signed_transaction(
+        &self,
+        network_identifier: NetworkIdentifier,
+        signed_hex: &str,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        let signed_hex = strip_hex_prefix(signed_hex);
+        if signed_hex.is_empty() {
+            return Err(
+                ApiError::InvalidInput(Some("Signed transaction is empty".to_string())).into(),
+            );
+        }
+        let bytes = hex::decode(signed_hex).map_err(ApiError::from)?;
+        bcs::from_bytes::<SignedTransaction>(&bytes)
+            .map_err(|_| ApiError::deserialization_failed("SignedTransaction"))?;
+
+        self.submit_transaction(network_identifier, signed_hex.to_string())
+            .await
+    }
+
+    /// Submits a transaction to the first healthy server, starting with the main one
+    ///
+    /// Servers must be within `sync_depth` blocks of the main server's latest block.  The
+    /// fallbacks are set with [`RosettaClientBuilder::fallback_addresses`]
+    pub async fn submit_with_health_check(
+        &self,
+        network_identifier: NetworkIdentifier,
+        signed_transaction: String,
+        sync_depth: u64,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        let min_block_index = self
+            .current_block_index(network_identifier.clone())
+            .await?
+            .saturating_sub(sync_depth);
+
+        let mut last_error = None;
+        let addresses = std::iter::once(&self.address).chain(self.fallback_addresses.iter());
+        for address in addresses {
+            let client = RosettaClient {
+                address: address.clone(),
+                ..self.clone()
+            };
+            match client
+                .health_check(network_identifier.clone(), Some(min_block_index))
+                .await
+            {
+                Ok(()) => {
+                    return client
+                        .submit_transaction(network_identifier, signed_transaction)
+                        .await
+                }
+                Err(err) => last_error = Some(format!("{}: {}", address, err)),
+            }
         }
+
+        Err(ApiError::NodeIsOffline(last_error).into())
     }
 
     /// Submit a transaction to the blockchain
@@ -461,27 +4599,1019 @@ impl RosettaClient {
         network_identifier: NetworkIdentifier,
         signed_transaction: String,
     ) -> anyhow::Result<TransactionIdentifier> {
-        Ok(self
+        let result = self
             .submit(&ConstructionSubmitRequest {
                 network_identifier,
                 signed_transaction,
             })
-            .await?
-            .transaction_identifier)
+            .await;
+
+        // A full mempool means the network is congested, and cached gas prices are stale
+        if let (Err(err), Some(metadata_cache)) = (&result, &self.metadata_cache) {
+            if err.downcast_ref::<Error>().map(|error| error.code)
+                == Some(ApiError::MempoolIsFull(None).code())
+            {
+                metadata_cache.invalidate();
+            }
+        }
+
+        Ok(result?.transaction_identifier)
     }
 }
 
-/// Converts a value to a Rosetta [`Amount`]
-///
-/// Only works with the native coin
-fn val_to_amount(amount: u64, withdraw: bool) -> Amount {
-    let value = if withdraw {
-        format!("-{}", amount)
-    } else {
-        amount.to_string()
-    };
-    Amount {
-        value,
-        currency: native_coin(),
+impl ConstructionFlowBuilder {
+    pub fn set_operations(mut self, operations: Vec<Operation>) -> Self {
+        self.operations = operations;
+        self
+    }
+
+    pub fn set_fee_policy(mut self, fee_policy: MaxFeePolicy) -> Self {
+        self.fee_policy = fee_policy;
+        self
+    }
+
+    pub fn set_expiry(mut self, expiry_time_secs: u64) -> Self {
+        self.expiry_time_secs = Some(expiry_time_secs);
+        self
+    }
+
+    pub fn set_sequence_number(mut self, sequence_number: u64) -> Self {
+        self.sequence_number = Some(sequence_number);
+        self
+    }
+
+    /// Estimates the fee and finds the signers, without needing any keys
+    pub async fn preview(&self) -> anyhow::Result<ConstructionPreview> {
+        let expiry_time_secs = self.check_ready()?;
+        let (signers, preprocess_response) = self
+            .client
+            .preprocess_only(
+                self.network_identifier.clone(),
+                self.operations.clone(),
+                self.fee_policy.max_fee,
+                self.fee_policy.fee_multiplier as f64,
+                expiry_time_secs,
+                self.sequence_number,
+            )
+            .await?;
+        let options = preprocess_response
+            .options
+            .ok_or_else(|| anyhow!("No metadata options returned from preprocess response"))?;
+
+        // The server doesn't use the public keys for metadata, so none are needed yet
+        let response = self
+            .client
+            .metadata(&ConstructionMetadataRequest {
+                network_identifier: self.network_identifier.clone(),
+                options,
+                public_keys: Vec::new(),
+            })
+            .await?;
+        let estimated_fee = match response
+            .suggested_fee
+            .and_then(|fees| fees.into_iter().find(|fee| fee.currency == native_coin()))
+        {
+            Some(fee) => fee,
+            // Older servers don't suggest a fee, so compute it the same way
+            None => format_amount(
+                i128::from(response.metadata.max_gas)
+                    * i128::from(response.metadata.gas_price_per_unit),
+                native_coin(),
+            ),
+        };
+
+        Ok(ConstructionPreview {
+            signers,
+            estimated_fee,
+        })
+    }
+
+    /// Builds and signs the transaction, there must be a key for every signer in the preview
+    pub async fn sign(
+        &self,
+        keys: &HashMap<AccountAddress, SigningKey<'_>>,
+    ) -> anyhow::Result<SignedConstruction> {
+        let expiry_time_secs = self.check_ready()?;
+        let (metadata, public_keys) = self
+            .client
+            .metadata_for_ops(
+                self.network_identifier.clone(),
+                self.operations.clone(),
+                self.fee_policy.max_fee,
+                self.fee_policy.fee_multiplier,
+                expiry_time_secs,
+                self.sequence_number,
+                keys,
+            )
+            .await?;
+        let response = self
+            .client
+            .unsigned_transaction(
+                self.network_identifier.clone(),
+                self.operations.clone(),
+                metadata.metadata,
+                public_keys,
+            )
+            .await?;
+        let signed_transaction = self
+            .client
+            .sign_transaction(
+                self.network_identifier.clone(),
+                keys,
+                response,
+                self.operations.clone(),
+            )
+            .await?;
+
+        Ok(SignedConstruction {
+            network_identifier: self.network_identifier.clone(),
+            signed_transaction,
+        })
+    }
+
+    /// Submits a transaction signed by [`ConstructionFlowBuilder::sign`]
+    pub async fn submit(
+        &self,
+        signed: SignedConstruction,
+    ) -> anyhow::Result<TransactionIdentifier> {
+        if signed.network_identifier != self.network_identifier {
+            return Err(ApiError::NetworkIdentifierMismatch.into());
+        }
+
+        self.client
+            .submit_transaction(signed.network_identifier, signed.signed_transaction)
+            .await
+    }
+
+    /// Checks the transaction has everything it needs, returning the expiry time
+    fn check_ready(&self) -> ApiResult<u64> {
+        if self.operations.is_empty() {
+            return Err(ApiError::InvalidOperations);
+        }
+
+        let expiry_time_secs = self.expiry_time_secs.ok_or_else(|| {
+            ApiError::InvalidExpiryTime(Some("Expiry time must be set".to_string()))
+        })?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| ApiError::InternalError(Some(err.to_string())))?
+            .as_secs();
+        if expiry_time_secs <= now {
+            return Err(ApiError::TransactionExpired(Some(format!(
+                "Expiry time {} is in the past",
+                expiry_time_secs
+            ))));
+        }
+
+        Ok(expiry_time_secs)
+    }
+}
+
+/// Servers without an optional endpoint won't respond with a Rosetta error, so treat it as offline
+fn not_enabled_as_offline(err: anyhow::Error) -> anyhow::Error {
+    match err.downcast_ref::<Error>() {
+        Some(error) if error.code == ApiError::InternalError(None).code() => {
+            ApiError::NodeIsOffline(None).into()
+        }
+        _ => err,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{CreateAccount, InternalOperation, MetadataOptions, RecoveryAction};
+    use std::sync::atomic::AtomicUsize;
+    use warp::Filter;
+
+    fn metadata_response(gas_price_per_unit: u64) -> ConstructionMetadataResponse {
+        ConstructionMetadataResponse {
+            metadata: ConstructionMetadata {
+                sequence_number: 0,
+                max_gas: 10000,
+                gas_price_per_unit,
+                expiry_time_secs: None,
+                min_gas_price_per_unit: None,
+            },
+            suggested_fee: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metadata_cache() {
+        let cache = MetadataCache::new(Duration::from_secs(60));
+        let network_identifier = NetworkIdentifier::from(aptos_types::chain_id::ChainId::test());
+        let operations = vec![Operation::create_account(
+            0,
+            None,
+            AccountAddress::from_hex_literal("0x2").unwrap(),
+            AccountAddress::ONE,
+        )];
+        let fetches = AtomicUsize::new(0);
+        let fetch = |gas_price_per_unit: u64| {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(metadata_response(gas_price_per_unit)) }
+        };
+
+        // The second call is served from the cache, without fetching
+        let first = cache
+            .get_or_fetch(&network_identifier, &operations, 10000, 1, Some(0), || {
+                fetch(100)
+            })
+            .await
+            .unwrap();
+        let second = cache
+            .get_or_fetch(&network_identifier, &operations, 10000, 1, Some(0), || {
+                fetch(200)
+            })
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(1, fetches.load(Ordering::SeqCst));
+
+        // Without a sequence number the server is always asked, for its sequence number
+        let unsequenced = cache
+            .get_or_fetch(&network_identifier, &operations, 10000, 1, None, || {
+                fetch(100)
+            })
+            .await
+            .unwrap();
+        assert_eq!(first, unsequenced);
+        assert_eq!(2, fetches.load(Ordering::SeqCst));
+
+        // Invalidating forces a new fetch
+        cache.invalidate();
+        let third = cache
+            .get_or_fetch(&network_identifier, &operations, 10000, 1, Some(0), || {
+                fetch(200)
+            })
+            .await
+            .unwrap();
+        assert_eq!(metadata_response(200), third);
+        assert_eq!(3, fetches.load(Ordering::SeqCst));
+
+        // A different fee multiplier gets its own gas price, rather than the cached one
+        let replacement = cache
+            .get_or_fetch(&network_identifier, &operations, 10000, 3, Some(0), || {
+                fetch(300)
+            })
+            .await
+            .unwrap();
+        assert_eq!(metadata_response(300), replacement);
+        assert_eq!(4, fetches.load(Ordering::SeqCst));
+
+        // As does a different max fee
+        let higher_max_fee = cache
+            .get_or_fetch(&network_identifier, &operations, 20000, 3, Some(0), || {
+                fetch(400)
+            })
+            .await
+            .unwrap();
+        assert_eq!(metadata_response(400), higher_max_fee);
+        assert_eq!(5, fetches.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_metadata_for_ops_cache() {
+        let private_key = Ed25519PrivateKey::try_from([1u8; 32].as_ref()).unwrap();
+        let sender = AuthenticationKey::ed25519(&private_key.public_key()).derived_address();
+        let new_account = AccountAddress::from_hex_literal("0x2").unwrap();
+
+        let preprocess = warp::path!("construction" / "preprocess")
+            .and(warp::post())
+            .map(move || {
+                warp::reply::json(&ConstructionPreprocessResponse {
+                    options: Some(MetadataOptions {
+                        internal_operation: InternalOperation::CreateAccount(CreateAccount {
+                            sender,
+                            new_account,
+                        }),
+                        max_gas: 10000,
+                        gas_price_per_unit: 1,
+                        expiry_time_secs: None,
+                        sequence_number: None,
+                    }),
+                    required_public_keys: Some(vec![sender.into()]),
+                })
+            });
+        // Each fetch sees the next sequence number, as if the previous transaction was submitted
+        let fetches = Arc::new(AtomicU64::new(0));
+        let route_fetches = fetches.clone();
+        let metadata = warp::path!("construction" / "metadata")
+            .and(warp::post())
+            .map(move || {
+                let mut response = metadata_response(100);
+                response.metadata.sequence_number = route_fetches.fetch_add(1, Ordering::SeqCst);
+                response.metadata.expiry_time_secs = Some(0);
+                warp::reply::json(&response)
+            });
+        let (address, server) =
+            warp::serve(preprocess.or(metadata)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = RosettaClientBuilder::new(Url::parse(&format!("http://{}", address)).unwrap())
+            .with_metadata_cache(Duration::from_secs(60))
+            .build_unchecked();
+        let network_identifier = NetworkIdentifier::from(aptos_types::chain_id::ChainId::test());
+        let operations = vec![Operation::create_account(0, None, new_account, sender)];
+        let mut keys = HashMap::new();
+        keys.insert(sender, SigningKey::from(&private_key));
+        let metadata_for_ops = |sequence_number| {
+            client.metadata_for_ops(
+                network_identifier.clone(),
+                operations.clone(),
+                10000,
+                1,
+                0,
+                sequence_number,
+                &keys,
+            )
+        };
+
+        // Without a sequence number, each transaction gets a fresh one from the server
+        let (first, _) = metadata_for_ops(None).await.unwrap();
+        let (second, _) = metadata_for_ops(None).await.unwrap();
+        assert_eq!(0, first.metadata.sequence_number);
+        assert_eq!(1, second.metadata.sequence_number);
+        assert_eq!(2, fetches.load(Ordering::SeqCst));
+
+        // With one, the cached gas fields are reused without asking the server
+        let (third, _) = metadata_for_ops(Some(5)).await.unwrap();
+        assert_eq!(5, third.metadata.sequence_number);
+        assert_eq!(100, third.metadata.gas_price_per_unit);
+        assert_eq!(2, fetches.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_preprocess_batch() {
+        const NUM_TRANSACTIONS: u64 = 100;
+        const SEQ_START: u64 = 5;
+
+        // The mock server echoes the sequence number back as the signer, to check the ordering
+        let route = warp::path!("construction" / "preprocess")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(|request: ConstructionPreprocessRequest| {
+                let sequence_number = request.metadata.unwrap().sequence_number.unwrap();
+                warp::reply::json(&ConstructionPreprocessResponse {
+                    options: None,
+                    required_public_keys: Some(vec![AccountAddress::from_hex_literal(&format!(
+                        "0x{:x}",
+                        sequence_number
+                    ))
+                    .unwrap()
+                    .into()]),
+                })
+            });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = RosettaClient::new(Url::parse(&format!("http://{}", address)).unwrap());
+        let network_identifier = NetworkIdentifier::from(aptos_types::chain_id::ChainId::test());
+        let operation_sets = (0..NUM_TRANSACTIONS)
+            .map(|_| {
+                vec![Operation::create_account(
+                    0,
+                    None,
+                    AccountAddress::from_hex_literal("0x2").unwrap(),
+                    AccountAddress::ONE,
+                )]
+            })
+            .collect();
+
+        let results = client
+            .preprocess_batch(network_identifier, operation_sets, 10000, 1.0, 0, SEQ_START)
+            .await;
+        assert_eq!(NUM_TRANSACTIONS as usize, results.len());
+        for (sequence_number, result) in (SEQ_START..).zip(results) {
+            let signers = result.unwrap().required_public_keys.unwrap();
+            assert_eq!(
+                AccountAddress::from_hex_literal(&format!("0x{:x}", sequence_number)).unwrap(),
+                signers[0].account_address().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_consensus_failure() {
+        let sync_status = |current_index: u64, target_index: u64, synced: bool| {
+            serde_json::from_value::<SyncStatus>(serde_json::json!({
+                "current_index": current_index,
+                "target_index": target_index,
+                "synced": synced,
+            }))
+            .unwrap()
+        };
+
+        assert!(consensus_failure(&sync_status(1000, 1000, true)).is_none());
+        assert!(consensus_failure(&sync_status(900, 1000, false)).is_none());
+        assert!(consensus_failure(&sync_status(500, 1000, true)).is_none());
+        let err = consensus_failure(&sync_status(899, 1000, false)).unwrap();
+        assert!(
+            matches!(err, ApiError::ConsensusFailure(Some(ref details)) if details.contains("101"))
+        );
+        assert!(err.retriable());
+    }
+
+    #[tokio::test]
+    async fn test_paginate_events() {
+        const EVENTS_PER_TRANSACTION: u64 = 10;
+
+        // 105 transactions of 10 events each is 1050 events
+        let pages: Vec<Vec<RosettaEvent>> =
+            paginate_events(0, 104, 100, |start, limit| async move {
+                Ok::<Vec<Vec<RosettaEvent>>, anyhow::Error>(
+                    (start..start + u64::from(limit))
+                        .map(|version| {
+                            (0..EVENTS_PER_TRANSACTION)
+                                .map(|sequence_number| RosettaEvent {
+                                    version,
+                                    sequence_number,
+                                    event_type: "0x1::test::TestEvent".to_string(),
+                                    data: serde_json::Value::Null,
+                                })
+                                .collect()
+                        })
+                        .collect(),
+                )
+            })
+            .map(|page| page.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(11, pages.len());
+        for page in &pages[..10] {
+            assert_eq!(100, page.len());
+        }
+        assert_eq!(50, pages[10].len());
+
+        // Events stay in order across pages
+        let events: Vec<_> = pages.into_iter().flatten().collect();
+        for (i, event) in events.iter().enumerate() {
+            assert_eq!(i as u64 / EVENTS_PER_TRANSACTION, event.version);
+            assert_eq!(i as u64 % EVENTS_PER_TRANSACTION, event.sequence_number);
+        }
+    }
+
+    #[test]
+    fn test_apply_exchange_rate() {
+        // 1 APT at 2.5 of another coin with the same decimals, rounding down the remainder
+        assert_eq!(250_000_000, apply_exchange_rate(100_000_000, 5, 2).unwrap());
+        assert_eq!(1, apply_exchange_rate(3, 1, 2).unwrap());
+        // Withdrawals stay negative
+        assert_eq!(-10, apply_exchange_rate(-5, 2, 1).unwrap());
+
+        assert!(matches!(
+            apply_exchange_rate(1, 1, 0),
+            Err(ApiError::UnsupportedCurrency(_))
+        ));
+        assert!(matches!(
+            apply_exchange_rate(i128::MAX, 2, 1),
+            Err(ApiError::AmountOverflow(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_block_linkage() {
+        use crate::types::BlockIdentifier;
+
+        let block = |index: u64, hash: &str, parent_hash: &str| Block {
+            block_identifier: BlockIdentifier {
+                index,
+                hash: hash.to_string(),
+            },
+            parent_block_identifier: BlockIdentifier {
+                index: index.saturating_sub(1),
+                hash: parent_hash.to_string(),
+            },
+            timestamp: 0,
+            transactions: vec![],
+        };
+
+        let valid = vec![
+            block(1, "0x1", "0x0"),
+            block(2, "0x2", "0x1"),
+            block(3, "0x3", "0x2"),
+        ];
+        assert!(check_block_linkage(&valid).is_empty());
+
+        // Every broken link is reported, not just the first
+        let broken = vec![
+            block(1, "0x1", "0x0"),
+            block(2, "0x2", "0xbad"),
+            block(3, "0x3", "0x2"),
+            block(4, "0x4", "0xdead"),
+        ];
+        assert_eq!(
+            vec![
+                ChainError {
+                    block_index: 2,
+                    expected_parent_hash: "0x1".to_string(),
+                    actual_parent_hash: "0xbad".to_string(),
+                },
+                ChainError {
+                    block_index: 4,
+                    expected_parent_hash: "0x3".to_string(),
+                    actual_parent_hash: "0xdead".to_string(),
+                },
+            ],
+            check_block_linkage(&broken)
+        );
+    }
+
+    #[test]
+    fn test_aptos_coin_not_registered_recovery_hint() {
+        let account = AccountAddress::from_hex_literal("0xb0b").unwrap();
+
+        // The hint has to survive the round trip through the server's error
+        let missing_account = ApiError::aptos_coin_not_registered(account, false);
+        let missing_account = ApiError::from_response(
+            missing_account.status_code(),
+            JSON,
+            serde_json::to_vec(&missing_account.into_error())
+                .unwrap()
+                .into(),
+        );
+        assert_eq!(
+            RecoveryAction::CallCreateAccount,
+            missing_account.recovery_hint().action
+        );
+
+        let unregistered = ApiError::aptos_coin_not_registered(account, true);
+        assert_eq!(
+            RecoveryAction::CallRegisterCoin,
+            unregistered.recovery_hint().action
+        );
+        assert!(!unregistered.retriable());
+    }
+
+    #[tokio::test]
+    async fn test_get_table_item() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let route_calls = calls.clone();
+
+        // Version 1 has been pruned, and no other version has the item
+        let route = warp::path!("v1" / "tables" / String / "item")
+            .and(warp::post())
+            .and(warp::query::<HashMap<String, u64>>())
+            .map(move |_handle: String, query: HashMap<String, u64>| {
+                route_calls.fetch_add(1, Ordering::SeqCst);
+                let (status, body) = if query.get("ledger_version") == Some(&1) {
+                    (
+                        warp::http::StatusCode::GONE,
+                        serde_json::json!({
+                            "message": "Ledger version 1 has been pruned",
+                            "error_code": "version_pruned",
+                        }),
+                    )
+                } else {
+                    (
+                        warp::http::StatusCode::NOT_FOUND,
+                        serde_json::json!({
+                            "message": "Table item not found",
+                            "error_code": "table_item_not_found",
+                        }),
+                    )
+                };
+                warp::reply::with_status(warp::reply::json(&body), status)
+            });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let url = Url::parse(&format!("http://{}", address)).unwrap();
+        let client =
+            RosettaClient::new(url.clone()).with_rest_client(aptos_rest_client::Client::new(url));
+
+        let err = client
+            .get_table_item("0x1", "address", "u64", "0xb0b".into(), Some(2))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ApiError>(),
+            Some(ApiError::TableItemNotFound(_))
+        ));
+
+        let err = client
+            .get_table_item("0x1", "address", "u64", "0xb0b".into(), Some(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ApiError>(),
+            Some(ApiError::VersionPruned(_))
+        ));
+
+        // Errors aren't cached, so the same lookup is tried again
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        let err = client
+            .get_table_item("0x1", "address", "u64", "0xb0b".into(), Some(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ApiError>(),
+            Some(ApiError::VersionPruned(_))
+        ));
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_block_with_retries() {
+        use crate::types::BlockIdentifier;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let route_calls = calls.clone();
+
+        // Block 1 has been pruned, and block 2 only shows up on the third call
+        let route = warp::path!("block")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |request: BlockRequest| {
+                let calls = route_calls.fetch_add(1, Ordering::SeqCst);
+                let error = match request.block_identifier.unwrap().index {
+                    Some(1) => Some(ApiError::BlockPruned(None)),
+                    _ if calls < 2 => Some(ApiError::BlockNotFound(None)),
+                    _ => None,
+                };
+                if let Some(error) = error {
+                    let status = error.status_code();
+                    return warp::reply::with_status(
+                        warp::reply::json(&error.into_error()),
+                        status,
+                    );
+                }
+                let block_identifier = |index: u64| BlockIdentifier {
+                    index,
+                    hash: format!("0x{}", index),
+                };
+                warp::reply::with_status(
+                    warp::reply::json(&BlockResponse {
+                        block: Some(Block {
+                            block_identifier: block_identifier(2),
+                            parent_block_identifier: block_identifier(1),
+                            timestamp: 0,
+                            transactions: vec![],
+                        }),
+                        other_transactions: None,
+                    }),
+                    warp::http::StatusCode::OK,
+                )
+            });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = RosettaClient::new(Url::parse(&format!("http://{}", address)).unwrap());
+        let network_identifier = NetworkIdentifier::from(aptos_types::chain_id::ChainId::test());
+
+        let response = client
+            .block_with_retries(
+                network_identifier.clone(),
+                PartialBlockIdentifier::block_index(2),
+                Duration::from_secs(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(2, response.block.unwrap().block_identifier.index);
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+
+        // Pruned blocks aren't retried
+        let err = client
+            .block_with_retries(
+                network_identifier,
+                PartialBlockIdentifier::block_index(1),
+                Duration::from_secs(10),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            ApiError::BlockPruned(None).code(),
+            err.downcast_ref::<Error>().unwrap().code
+        );
+        assert_eq!(4, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_account_balance_changes() {
+        use crate::types::{AccountBalanceMetadata, BlockIdentifier};
+
+        let account = AccountAddress::from_hex_literal("0xb0b").unwrap();
+        let other = AccountAddress::from_hex_literal("0xa11ce").unwrap();
+        // Balance at the end of each block, to check the changes against
+        const BALANCES: [u64; 4] = [5000, 6000, 5690, 5685];
+
+        let block = move |index: u64| {
+            let operations = match index {
+                1 => vec![
+                    Operation::deposit(
+                        0,
+                        Some(OperationStatusType::Success),
+                        account,
+                        native_coin(),
+                        1000,
+                    ),
+                    Operation::withdraw(
+                        1,
+                        Some(OperationStatusType::Success),
+                        other,
+                        native_coin(),
+                        1000,
+                    ),
+                ],
+                2 => vec![
+                    Operation::withdraw(
+                        0,
+                        Some(OperationStatusType::Success),
+                        account,
+                        native_coin(),
+                        300,
+                    ),
+                    Operation::gas_fee(1, account, 10, 1),
+                ],
+                // A failed transfer only costs the fee
+                3 => vec![
+                    Operation::withdraw(
+                        0,
+                        Some(OperationStatusType::Failure),
+                        account,
+                        native_coin(),
+                        500,
+                    ),
+                    Operation::gas_fee(1, account, 5, 1),
+                ],
+                _ => vec![],
+            };
+            let block_identifier = |index: u64| BlockIdentifier {
+                index,
+                hash: format!("0x{}", index),
+            };
+            Block {
+                block_identifier: block_identifier(index),
+                parent_block_identifier: block_identifier(index.saturating_sub(1)),
+                timestamp: 0,
+                transactions: vec![RosettaTransaction {
+                    transaction_identifier: TransactionIdentifier {
+                        hash: format!("0x{}", index),
+                    },
+                    operations,
+                    related_transactions: None,
+                    metadata: None,
+                }],
+            }
+        };
+
+        let block_route = warp::path!("block")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |request: BlockRequest| {
+                let index = request.block_identifier.unwrap().index.unwrap();
+                warp::reply::json(&BlockResponse {
+                    block: Some(block(index)),
+                    other_transactions: None,
+                })
+            });
+        let balance_route = warp::path!("account" / "balance")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |request: AccountBalanceRequest| {
+                let index = request.block_identifier.unwrap().index.unwrap();
+                warp::reply::json(&AccountBalanceResponse {
+                    block_identifier: BlockIdentifier {
+                        index,
+                        hash: format!("0x{}", index),
+                    },
+                    balances: vec![Amount {
+                        value: BALANCES[index as usize].to_string(),
+                        currency: native_coin(),
+                    }],
+                    metadata: AccountBalanceMetadata { sequence_number: 0 },
+                })
+            });
+        let (address, server) =
+            warp::serve(block_route.or(balance_route)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = RosettaClient::new(Url::parse(&format!("http://{}", address)).unwrap());
+        let network_identifier = NetworkIdentifier::from(aptos_types::chain_id::ChainId::test());
+        let summary = client
+            .account_balance_changes(network_identifier.clone(), account, &native_coin(), 0, 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            BalanceChangeSummary {
+                net_change: 685,
+                total_received: 1000,
+                total_sent: 315,
+                transaction_count: 3,
+            },
+            summary
+        );
+
+        // The net change matches the difference in the balances
+        let mut balances = Vec::new();
+        for index in [0, 3] {
+            let response = client
+                .account_balance(&AccountBalanceRequest {
+                    network_identifier: network_identifier.clone(),
+                    account_identifier: account.into(),
+                    block_identifier: Some(PartialBlockIdentifier::block_index(index)),
+                    currencies: None,
+                })
+                .await
+                .unwrap();
+            balances.push(parse_amount(&response.balances[0]).unwrap());
+        }
+        assert_eq!(balances[1] - balances[0], summary.net_change);
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let retry_config = RetryConfig {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(100),
+            backoff_factor: 3.0,
+            max_total_delay: Duration::from_secs(2),
+        };
+        assert_eq!(Duration::from_millis(100), retry_config.delay(1));
+        assert_eq!(Duration::from_millis(300), retry_config.delay(2));
+        assert_eq!(Duration::from_millis(900), retry_config.delay(3));
+        // No single delay is longer than the total
+        assert_eq!(Duration::from_secs(2), retry_config.delay(4));
+        assert_eq!(Duration::from_secs(2), retry_config.delay(u32::MAX));
+    }
+
+    #[test]
+    fn test_signing_key() {
+        let private_key = Ed25519PrivateKey::try_from([1u8; 32].as_ref()).unwrap();
+        let signing_key = SigningKey::from(&private_key);
+        assert_eq!(CurveType::Edwards25519, signing_key.curve_type());
+        assert_eq!(SignatureType::Ed25519, signing_key.signature_type());
+        assert_eq!(
+            encode_public_key(&private_key.public_key()).unwrap(),
+            signing_key.public_key().unwrap()
+        );
+
+        let unsigned_transaction = RawTransaction::new_script(
+            AccountAddress::ONE,
+            0,
+            aptos_types::transaction::Script::new(vec![], vec![], vec![]),
+            1000,
+            100,
+            0,
+            aptos_types::chain_id::ChainId::test(),
+        );
+        let payload = SigningPayload {
+            address: None,
+            account_identifier: Some(AccountAddress::ONE.into()),
+            hex_bytes: hex::encode(unsigned_transaction.signing_message()),
+            signature_type: Some(SignatureType::Ed25519),
+        };
+        let signatures = signing_key.sign(&payload, &unsigned_transaction).unwrap();
+        assert_eq!(1, signatures.len());
+        assert_eq!(ED25519_SIGNATURE_LENGTH * 2, signatures[0].hex_bytes.len());
+    }
+
+    #[test]
+    fn test_multi_key_set() {
+        let private_key = |seed: u8| Ed25519PrivateKey::try_from([seed; 32].as_ref()).unwrap();
+        let public_keys: Vec<_> = (1..=3).map(|seed| private_key(seed).public_key()).collect();
+        let unsigned_transaction = RawTransaction::new_script(
+            AccountAddress::ONE,
+            0,
+            aptos_types::transaction::Script::new(vec![], vec![], vec![]),
+            1000,
+            100,
+            0,
+            aptos_types::chain_id::ChainId::test(),
+        );
+        let payload = SigningPayload {
+            address: None,
+            account_identifier: Some(AccountAddress::ONE.into()),
+            hex_bytes: hex::encode(unsigned_transaction.signing_message()),
+            signature_type: Some(SignatureType::Ed25519),
+        };
+
+        // 2 of 3 keys sign separately
+        let key_set =
+            MultiKeySet::new(public_keys.clone(), vec![private_key(1), private_key(3)], 2).unwrap();
+        assert_eq!(
+            AuthenticationKey::multi_ed25519(
+                &MultiEd25519PublicKey::new(public_keys.clone(), 2).unwrap()
+            )
+            .derived_address(),
+            key_set.account_address()
+        );
+        let signatures = SigningKey::from(&key_set)
+            .sign(&payload, &unsigned_transaction)
+            .unwrap();
+        assert_eq!(
+            vec![
+                encode_public_key(&public_keys[0]).unwrap(),
+                encode_public_key(&public_keys[2]).unwrap()
+            ],
+            signatures
+                .into_iter()
+                .map(|signature| signature.public_key)
+                .collect::<Vec<_>>()
+        );
+
+        // Not enough keys to meet the threshold
+        let key_set = MultiKeySet::new(public_keys.clone(), vec![private_key(2)], 2).unwrap();
+        let err = SigningKey::from(&key_set)
+            .sign(&payload, &unsigned_transaction)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ApiError>(),
+            Some(ApiError::UnsupportedSignatureCount(Some(1)))
+        ));
+
+        // Keys must be part of the set
+        assert!(matches!(
+            MultiKeySet::new(public_keys, vec![private_key(4)], 2),
+            Err(ApiError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_supported_features() {
+        let allow = |operation_types| Allow {
+            operation_statuses: vec![],
+            operation_types,
+            errors: vec![],
+            historical_balance_lookup: true,
+            timestamp_start_index: None,
+            call_methods: vec![],
+            balance_exemptions: vec![],
+            mempool_coins: false,
+            block_hash_case: None,
+            transaction_hash_case: None,
+            currencies: None,
+        };
+
+        // Every feature is built from operation types the server advertises
+        let advertised = allow(crate::network::allowed_operation_types());
+        assert!(RosettaFeature::Staking.is_supported_by(&advertised));
+        assert!(RosettaFeature::KeyRotation.is_supported_by(&advertised));
+        assert!(RosettaFeature::OfflineConstruction.is_supported_by(&advertised));
+
+        // A feature needs all of its operation types
+        let partial = allow(vec!["set_operator".to_string(), "rotate_key".to_string()]);
+        assert!(!RosettaFeature::Staking.is_supported_by(&partial));
+        assert!(RosettaFeature::KeyRotation.is_supported_by(&partial));
+        assert!(!RosettaFeature::KeyRotation.is_supported_by(&allow(vec![])));
+        assert!(!RosettaFeature::OfflineConstruction.is_supported_by(&partial));
+    }
+
+    #[tokio::test]
+    async fn test_make_call_retries() {
+        const MAX_ATTEMPTS: u32 = 4;
+        let attempts = Arc::new(RwLock::new(Vec::new()));
+        let route_attempts = attempts.clone();
+
+        // The mempool never empties, so every attempt fails
+        let route = warp::path!("network" / "list")
+            .and(warp::post())
+            .map(move || {
+                route_attempts.write().unwrap().push(Instant::now());
+                let error = ApiError::MempoolIsFull(None);
+                let status = error.status_code();
+                warp::reply::with_status(warp::reply::json(&error.into_error()), status)
+            });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let retry_config = RetryConfig {
+            max_attempts: MAX_ATTEMPTS,
+            initial_delay: Duration::from_millis(20),
+            backoff_factor: 2.0,
+            max_total_delay: Duration::from_secs(10),
+        };
+        let client = RosettaClient::with_retry(
+            Url::parse(&format!("http://{}", address)).unwrap(),
+            retry_config,
+        );
+        let err = client.network_list().await.unwrap_err();
+        assert_eq!(
+            ApiError::MempoolIsFull(None).code(),
+            err.downcast_ref::<Error>().unwrap().code
+        );
+
+        let delays: Vec<_> = {
+            let attempts = attempts.read().unwrap();
+            assert_eq!(MAX_ATTEMPTS as usize, attempts.len());
+            attempts
+                .windows(2)
+                .map(|pair| pair[1].duration_since(pair[0]))
+                .collect()
+        };
+        for (attempt, delay) in (1..).zip(&delays) {
+            assert!(*delay >= retry_config.delay(attempt));
+        }
+        // Jitter adds at most half, so doubling always outgrows it
+        for pair in delays.windows(2) {
+            assert!(pair[1] > pair[0]);
+        }
+
+        // Without a retry config, there's only one attempt
+        let client = RosettaClient::new(Url::parse(&format!("http://{}", address)).unwrap());
+        client.network_list().await.unwrap_err();
+        assert_eq!(MAX_ATTEMPTS as usize + 1, attempts.read().unwrap().len());
     }
 }
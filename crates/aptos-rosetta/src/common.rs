@@ -5,23 +5,113 @@ use crate::types::{aptos_coin_module_identifier, aptos_coin_resource_identifier}
 use crate::{
     error::{ApiError, ApiResult},
     types::{
-        Currency, CurrencyMetadata, MetadataRequest, NetworkIdentifier, PartialBlockIdentifier,
+        Amount, ConstructionMetadataResponse, Currency, CurrencyMetadata, CurveType,
+        MetadataRequest, NetworkIdentifier, Operation, OperationType, PartialBlockIdentifier,
+        PublicKey, Signature,
     },
     RosettaContext,
 };
-use aptos_crypto::{ValidCryptoMaterial, ValidCryptoMaterialStringExt};
+use anyhow::anyhow;
+use aptos_crypto::{
+    ed25519::{Ed25519PublicKey, ED25519_PUBLIC_KEY_LENGTH},
+    HashValue, ValidCryptoMaterial, ValidCryptoMaterialStringExt,
+};
 use aptos_logger::debug;
-use aptos_rest_client::{Account, Response};
+use aptos_rest_client::{aptos_api_types::U64, Account, Response};
 use aptos_sdk::move_types::language_storage::{StructTag, TypeTag};
-use aptos_types::{account_address::AccountAddress, chain_id::ChainId};
+use aptos_types::{
+    account_address::AccountAddress, chain_id::ChainId,
+    transaction::authenticator::AuthenticationKey,
+};
 use futures::future::BoxFuture;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::{convert::Infallible, fmt::LowerHex, future::Future, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::{Infallible, TryFrom},
+    fmt::LowerHex,
+    future::Future,
+    str::FromStr,
+};
 use warp::Filter;
 
 /// The year 2000 in milliseconds, as this is the lower limit for Rosetta API implementations
 pub const Y2K_MS: u64 = 946713600000;
 pub const BLOCKCHAIN: &str = "aptos";
+/// Maximum number of operations that can be built into a single transaction
+pub const MAX_OPERATIONS_PER_TRANSACTION: usize = 256;
+/// Maximum size of a single operation's metadata, serialized as JSON
+pub const MAX_OPERATION_METADATA_BYTES: usize = 4096;
+/// Domain separator for deriving an object's address from its creator and a seed
+pub const OBJECT_FROM_SEED_ADDRESS_SCHEME: u8 = 0xFE;
+
+/// Address of the Aptos framework
+pub const APTOS_FRAMEWORK_ADDRESS: AccountAddress = AccountAddress::ONE;
+/// Address of the Aptos standard library, which lives alongside the framework
+pub const APTOS_STDLIB_ADDRESS: AccountAddress = AccountAddress::ONE;
+/// Address of the original token standard
+pub const TOKEN_V1_ADDRESS: AccountAddress = special_address(0x3);
+/// Address of the object based token standard
+pub const TOKEN_V2_ADDRESS: AccountAddress = special_address(0x4);
+
+/// Well-known addresses that are controlled by the framework
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FrameworkAddresses {
+    pub aptos_framework: AccountAddress,
+    pub token_v1: AccountAddress,
+    pub token_v2: AccountAddress,
+    pub aptos_stdlib: AccountAddress,
+}
+
+pub const fn framework_addresses() -> FrameworkAddresses {
+    FrameworkAddresses {
+        aptos_framework: APTOS_FRAMEWORK_ADDRESS,
+        token_v1: TOKEN_V1_ADDRESS,
+        token_v2: TOKEN_V2_ADDRESS,
+        aptos_stdlib: APTOS_STDLIB_ADDRESS,
+    }
+}
+
+/// Checks if the address is one of the [`FrameworkAddresses`]
+pub fn is_framework_address(addr: AccountAddress) -> bool {
+    let addresses = framework_addresses();
+    addr == addresses.aptos_framework
+        || addr == addresses.token_v1
+        || addr == addresses.token_v2
+        || addr == addresses.aptos_stdlib
+}
+
+/// Address of the treasury and compliance account, `0xA550C18`
+pub const fn treasury_compliance_address() -> AccountAddress {
+    let mut bytes = [0u8; AccountAddress::LENGTH];
+    bytes[AccountAddress::LENGTH - 4] = 0x0A;
+    bytes[AccountAddress::LENGTH - 3] = 0x55;
+    bytes[AccountAddress::LENGTH - 2] = 0x0C;
+    bytes[AccountAddress::LENGTH - 1] = 0x18;
+    AccountAddress::new(bytes)
+}
+
+/// Address the core framework code is published at
+pub const fn core_code_address() -> AccountAddress {
+    APTOS_FRAMEWORK_ADDRESS
+}
+
+/// Checks if the address belongs to the system rather than a user
+///
+/// Useful for filtering internal framework transactions out of user facing operations
+pub fn is_treasury_or_system_address(addr: AccountAddress) -> bool {
+    addr == treasury_compliance_address()
+        || addr == core_code_address()
+        || addr == AccountAddress::ZERO
+        || is_framework_address(addr)
+}
+
+/// Builds a special address e.g. `0x3` from its last byte
+const fn special_address(last_byte: u8) -> AccountAddress {
+    let mut bytes = [0u8; AccountAddress::LENGTH];
+    bytes[AccountAddress::LENGTH - 1] = last_byte;
+    AccountAddress::new(bytes)
+}
 
 /// Checks the request network matches the server network
 pub fn check_network(
@@ -102,6 +192,145 @@ pub async fn get_account(
         .map_err(|_| ApiError::AccountNotFound(Some(address.to_string())))
 }
 
+/// Retrieves a Move resource, and deserializes its data into `T`
+///
+/// `resource_type` is the full type e.g. `0x1::stake::StakePool`, and `None` for the version
+/// is the latest version.  Missing resources and pruned versions are surfaced as
+/// [`ApiError::ResourceNotFound`] and [`ApiError::VersionPruned`] respectively.  The response's
+/// state is kept, so callers can check which chain and version it came from.
+pub async fn get_resource<T: DeserializeOwned>(
+    rest_client: &aptos_rest_client::Client,
+    account: AccountAddress,
+    resource_type: &str,
+    ledger_version: Option<u64>,
+) -> anyhow::Result<aptos_rest_client::Response<T>> {
+    const ENCODE_CHARS: &AsciiSet = &CONTROLS.add(b'<').add(b'>');
+    let encoded_resource_type = utf8_percent_encode(resource_type, ENCODE_CHARS).to_string();
+
+    let response = if let Some(version) = ledger_version {
+        rest_client
+            .get_account_resource_at_version(account, &encoded_resource_type, version)
+            .await
+    } else {
+        rest_client
+            .get_account_resource(account, &encoded_resource_type)
+            .await
+    }
+    .map_err(ApiError::from)?;
+
+    let (resource, state) = response.into_parts();
+    if let Some(resource) = resource {
+        let data = serde_json::from_value(resource.data)
+            .map_err(|_| ApiError::deserialization_failed(resource_type))?;
+        Ok(aptos_rest_client::Response::new(data, state))
+    } else {
+        Err(
+            ApiError::ResourceNotFound(Some(format!("{} not found at {}", resource_type, account)))
+                .into(),
+        )
+    }
+}
+
+/// Retrieves the onchain gas parameters from `0x1::gas_schedule::GasSchedule`, by name
+pub async fn get_gas_schedule_entries(
+    rest_client: &aptos_rest_client::Client,
+    ledger_version: Option<u64>,
+) -> anyhow::Result<HashMap<String, u64>> {
+    /// Types for deserializing the gas schedule
+    #[derive(Deserialize)]
+    struct GasEntry {
+        key: String,
+        val: U64,
+    }
+    #[derive(Deserialize)]
+    struct GasSchedule {
+        entries: Vec<GasEntry>,
+    }
+
+    let gas_schedule: GasSchedule = get_resource(
+        rest_client,
+        AccountAddress::ONE,
+        "0x1::gas_schedule::GasSchedule",
+        ledger_version,
+    )
+    .await?
+    .into_inner();
+    Ok(gas_schedule
+        .entries
+        .into_iter()
+        .map(|entry| (entry.key, entry.val.0))
+        .collect())
+}
+
+/// Retrieves the minimum stake a stake pool needs, from the staking config
+pub async fn get_minimum_stake(rest_client: &aptos_rest_client::Client) -> ApiResult<u64> {
+    /// Type for deserializing the staking config
+    #[derive(Deserialize)]
+    struct StakingConfig {
+        minimum_stake: U64,
+    }
+
+    let staking_config: StakingConfig = get_resource(
+        rest_client,
+        AccountAddress::ONE,
+        "0x1::staking_config::StakingConfig",
+        None,
+    )
+    .await?
+    .into_inner();
+    Ok(staking_config.minimum_stake.0)
+}
+
+/// Retrieves the stake in a stake pool that counts towards the minimum stake
+///
+/// This is the active stake and the stake that becomes active next epoch, a pool that doesn't
+/// exist yet has none
+pub async fn get_pool_stake(
+    rest_client: &aptos_rest_client::Client,
+    pool_address: AccountAddress,
+) -> ApiResult<u64> {
+    /// Types for deserializing the stake pool
+    #[derive(Deserialize)]
+    struct Coin {
+        value: U64,
+    }
+    #[derive(Deserialize)]
+    struct StakePool {
+        active: Coin,
+        pending_active: Coin,
+    }
+
+    match get_resource::<StakePool>(rest_client, pool_address, "0x1::stake::StakePool", None).await
+    {
+        Ok(stake_pool) => Ok(stake_pool
+            .into_inner()
+            .active
+            .value
+            .0
+            .saturating_add(stake_pool.pending_active.value.0)),
+        Err(err) => match err.downcast::<ApiError>() {
+            Ok(ApiError::ResourceNotFound(_)) | Ok(ApiError::AccountNotFound(_)) => Ok(0),
+            Ok(err) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+    }
+}
+
+/// Checks a pool's stake is at least the minimum stake once the amount is added
+///
+/// The minimum applies to the pool's total, so any top up of a pool already above it is fine
+pub fn check_minimum_stake(pool_stake: u64, amount: u64, minimum_stake: u64) -> ApiResult<()> {
+    let total_stake = pool_stake.saturating_add(amount);
+    if total_stake < minimum_stake {
+        Err(ApiError::InsufficientStake(Some(format!(
+            "Stake of {} is below the minimum stake of {}",
+            total_stake, minimum_stake
+        ))))
+    } else {
+        Ok(())
+    }
+}
+
 /// Retrieve the timestamp according ot the Rosetta spec (milliseconds)
 pub fn get_timestamp(timestamp_usecs: u64) -> u64 {
     // note: timestamps are in microseconds, so we convert to milliseconds
@@ -129,6 +358,33 @@ pub fn decode_bcs<T: DeserializeOwned>(str: &str, type_name: &'static str) -> Ap
     bcs::from_bytes(&bytes).map_err(|_| ApiError::deserialization_failed(type_name))
 }
 
+/// Decodes a hex string, which must be exactly `expected_bytes` long
+///
+/// Invalid hex is a [`ApiError::DeserializationFailed`], while valid hex of the wrong length is a
+/// [`ApiError::InvalidHexLength`] for `field`
+pub fn validate_hex_bytes(
+    hex: &str,
+    expected_bytes: usize,
+    field: &'static str,
+) -> Result<Vec<u8>, ApiError> {
+    let bytes = hex::decode(strip_hex_prefix(hex))?;
+    if bytes.len() != expected_bytes {
+        return Err(ApiError::InvalidHexLength {
+            expected_bytes,
+            got_bytes: bytes.len(),
+            field,
+        });
+    }
+    Ok(bytes)
+}
+
+/// Decodes a hex encoded ed25519 public key, checking its length first
+pub fn decode_ed25519_public_key(hex: &str, field: &'static str) -> ApiResult<Ed25519PublicKey> {
+    let bytes = validate_hex_bytes(hex, ED25519_PUBLIC_KEY_LENGTH, field)?;
+    Ed25519PublicKey::try_from(bytes.as_slice())
+        .map_err(|_| ApiError::deserialization_failed("Ed25519PublicKey"))
+}
+
 pub fn decode_key<T: DeserializeOwned + ValidCryptoMaterial>(
     str: &str,
     type_name: &'static str,
@@ -136,6 +392,63 @@ pub fn decode_key<T: DeserializeOwned + ValidCryptoMaterial>(
     T::from_encoded_string(str).map_err(|_| ApiError::deserialization_failed(type_name))
 }
 
+/// Converts an [`Ed25519PublicKey`] to a Rosetta [`PublicKey`]
+pub fn encode_public_key(key: &Ed25519PublicKey) -> anyhow::Result<PublicKey> {
+    Ok(PublicKey {
+        hex_bytes: key.to_encoded_string()?,
+        curve_type: CurveType::Edwards25519,
+    })
+}
+
+/// Converts a Rosetta [`PublicKey`] back to an [`Ed25519PublicKey`]
+pub fn decode_public_key(rosetta_key: &PublicKey) -> anyhow::Result<Ed25519PublicKey> {
+    if rosetta_key.curve_type != CurveType::Edwards25519 {
+        return Err(anyhow!("Invalid curve type"));
+    }
+
+    Ok(decode_ed25519_public_key(
+        &rosetta_key.hex_bytes,
+        "public_key",
+    )?)
+}
+
+/// Checks that a Rosetta [`PublicKey`] is a hex encoded ed25519 key, before trying to decode it
+pub fn validate_public_key(key: &PublicKey) -> Result<(), ApiError> {
+    if key.curve_type != CurveType::Edwards25519 {
+        return Err(ApiError::InvalidPublicKeyFormat(Some(format!(
+            "Curve type {:?} is not supported, expected {:?}",
+            key.curve_type,
+            CurveType::Edwards25519
+        ))));
+    }
+
+    match validate_hex_bytes(&key.hex_bytes, ED25519_PUBLIC_KEY_LENGTH, "public_key") {
+        Ok(_) => Ok(()),
+        Err(err @ ApiError::InvalidHexLength { .. }) => Err(err),
+        Err(_) => Err(ApiError::InvalidPublicKeyFormat(Some(format!(
+            "Key {} is not hex encoded",
+            key.hex_bytes
+        )))),
+    }
+}
+
+/// Checks that no two signatures are from the same account
+pub fn check_conflicting_signers(signatures: &[Signature]) -> ApiResult<()> {
+    let mut signers = HashSet::new();
+    for signature in signatures {
+        let public_key = decode_ed25519_public_key(&signature.public_key.hex_bytes, "public_key")?;
+        let signer = AuthenticationKey::ed25519(&public_key).derived_address();
+        if !signers.insert(signer) {
+            return Err(ApiError::ConflictingSigners(Some(format!(
+                "Account {} signed more than once",
+                signer
+            ))));
+        }
+    }
+
+    Ok(())
+}
+
 const DEFAULT_COIN: &str = "APT";
 const DEFAULT_DECIMALS: u64 = 8;
 
@@ -166,6 +479,249 @@ pub fn is_native_coin(currency: &Currency) -> ApiResult<()> {
     }
 }
 
+/// Derives the address of an object created by `creator` with the given seed
+///
+/// The address is `SHA3-256(creator || seed || OBJECT_FROM_SEED_ADDRESS_SCHEME)`
+pub fn derive_object_address(creator: AccountAddress, seed: &[u8]) -> AccountAddress {
+    let mut bytes = creator.to_vec();
+    bytes.extend_from_slice(seed);
+    bytes.push(OBJECT_FROM_SEED_ADDRESS_SCHEME);
+    AccountAddress::new(*HashValue::sha3_256_of(&bytes))
+}
+
+/// Derives the address of a named object, where the name is used as the seed
+pub fn derive_named_object_address(creator: AccountAddress, seed_str: &str) -> AccountAddress {
+    derive_object_address(creator, seed_str.as_bytes())
+}
+
+/// Checks that a metadata response has everything needed to build a transaction
+///
+/// A gas price of zero is valid when the chain's minimum is zero, so it's only treated as missing
+/// when the server reports a higher minimum
+pub fn validate_construction_metadata(
+    metadata: &ConstructionMetadataResponse,
+) -> Result<(), ApiError> {
+    let metadata = &metadata.metadata;
+    let missing_field =
+        if metadata.gas_price_per_unit == 0 && metadata.min_gas_price_per_unit.unwrap_or(0) > 0 {
+            "gas_price_per_unit"
+        } else if metadata.expiry_time_secs.is_none() {
+            "expiry_time_secs"
+        } else {
+            return Ok(());
+        };
+
+    Err(ApiError::PartialMetadata(Some(format!(
+        "Metadata is missing {}",
+        missing_field
+    ))))
+}
+
+/// Derives the address of a resource account created by `creator` with the given seed
+///
+/// The address is `SHA3-256(creator || seed)`, matching `0x1::account::create_resource_account`
+pub fn derive_resource_account_address(creator: AccountAddress, seed: &[u8]) -> AccountAddress {
+    let mut bytes = creator.to_vec();
+    bytes.extend_from_slice(seed);
+    AccountAddress::new(*HashValue::sha3_256_of(&bytes))
+}
+
+/// Checks operations for problems that can be found without looking at the chain
+pub fn pre_validate_operations(operations: &[Operation]) -> ApiResult<()> {
+    if operations.len() > MAX_OPERATIONS_PER_TRANSACTION {
+        return Err(ApiError::MaximumBlockSizeExceeded(Some(format!(
+            "{} operations exceeds the limit of {}",
+            operations.len(),
+            MAX_OPERATIONS_PER_TRANSACTION
+        ))));
+    }
+
+    let supported_types = OperationType::all();
+    for operation in operations {
+        if !supported_types
+            .iter()
+            .any(|supported_type| supported_type.to_string() == operation.operation_type)
+        {
+            return Err(ApiError::UnsupportedOperationType(Some(
+                operation.operation_type.clone(),
+            )));
+        }
+
+        if let Some(ref metadata) = operation.metadata {
+            let size = serde_json::to_vec(metadata)
+                .map_err(|err| ApiError::DeserializationFailed(Some(err.to_string())))?
+                .len();
+            if size > MAX_OPERATION_METADATA_BYTES {
+                return Err(ApiError::OperationMetadataTooLarge(Some(format!(
+                    "Operation {} metadata is {} bytes, exceeding the limit of {}",
+                    operation.operation_identifier.index, size, MAX_OPERATION_METADATA_BYTES
+                ))));
+            }
+        }
+    }
+
+    // Sort the indices so any duplicates end up next to each other
+    let mut indices: Vec<u64> = operations
+        .iter()
+        .map(|operation| operation.operation_identifier.index)
+        .collect();
+    indices.sort_unstable();
+    if let Some(window) = indices.windows(2).find(|window| window[0] == window[1]) {
+        return Err(ApiError::DuplicateOperation(Some(format!(
+            "Operation index {} is duplicated",
+            window[0]
+        ))));
+    }
+
+    validate_balance_neutral(operations)
+}
+
+/// The account sending the operation, deposits are received so they have no sender
+fn operation_sender(operation: &Operation) -> ApiResult<Option<AccountAddress>> {
+    if operation.operation_type == OperationType::Deposit.to_string() {
+        return Ok(None);
+    }
+
+    match operation
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.sender())
+        .or(operation.account.as_ref())
+    {
+        Some(account) => Ok(Some(account.account_address()?)),
+        None => Ok(None),
+    }
+}
+
+/// Groups operations by the account that sends them, deposits are left out
+pub fn group_operations_by_sender(
+    operations: &[Operation],
+) -> HashMap<AccountAddress, Vec<&Operation>> {
+    let mut groups: HashMap<AccountAddress, Vec<&Operation>> = HashMap::new();
+    for operation in operations {
+        if let Ok(Some(sender)) = operation_sender(operation) {
+            groups.entry(sender).or_default().push(operation);
+        }
+    }
+    groups
+}
+
+/// Checks that all operations are sent by one account, and returns it
+pub fn validate_single_sender_operations(operations: &[Operation]) -> ApiResult<AccountAddress> {
+    let mut sender = None;
+    for operation in operations {
+        match (sender, operation_sender(operation)?) {
+            (_, None) => {}
+            (None, Some(operation_sender)) => sender = Some(operation_sender),
+            (Some(sender), Some(operation_sender)) if sender != operation_sender => {
+                return Err(ApiError::InvalidInput(Some(format!(
+                    "Operations have multiple senders {} and {}",
+                    sender, operation_sender
+                ))));
+            }
+            _ => {}
+        }
+    }
+
+    sender.ok_or_else(|| ApiError::InvalidInput(Some("Operations have no sender".to_string())))
+}
+
+/// Sorts operations by index, and reassigns the indices to be 0 to n
+///
+/// Related operations are updated to point at the new indices
+pub fn renumber_operations(mut operations: Vec<Operation>) -> Vec<Operation> {
+    operations.sort_by_key(|operation| operation.operation_identifier.index);
+
+    let mut new_indices: HashMap<u64, u64> = HashMap::new();
+    for (new_index, operation) in (0u64..).zip(operations.iter_mut()) {
+        new_indices
+            .entry(operation.operation_identifier.index)
+            .or_insert(new_index);
+        operation.operation_identifier.index = new_index;
+    }
+
+    for operation in operations.iter_mut() {
+        if let Some(related_operations) = operation.related_operations.as_mut() {
+            for related_operation in related_operations.iter_mut() {
+                if let Some(new_index) = new_indices.get(&related_operation.index) {
+                    related_operation.index = *new_index;
+                }
+            }
+        }
+    }
+
+    operations
+}
+
+/// Checks that the operations don't create or destroy any currency, excluding gas fees
+///
+/// Every currency's withdraws must exactly cancel out its deposits
+pub fn validate_balance_neutral(operations: &[Operation]) -> ApiResult<()> {
+    let fee_type = OperationType::Fee.to_string();
+    let mut net_balances: HashMap<&Currency, i128> = HashMap::new();
+    for operation in operations {
+        if operation.operation_type == fee_type {
+            continue;
+        }
+
+        if let Some(ref amount) = operation.amount {
+            let value = i128::from_str(&amount.value).map_err(|_| {
+                ApiError::InvalidTransferOperations(Some("Operation amount is invalid"))
+            })?;
+            let net_balance = net_balances.entry(&amount.currency).or_default();
+            *net_balance = net_balance.checked_add(value).ok_or_else(|| {
+                ApiError::AmountOverflow(Some(format!("{} + {}", net_balance, value)))
+            })?;
+        }
+    }
+
+    if net_balances.values().all(|net_balance| *net_balance == 0) {
+        Ok(())
+    } else {
+        Err(ApiError::InvalidTransferOperations(Some(
+            "net balance is non-zero",
+        )))
+    }
+}
+
+/// Parses the value of an amount, which is negative for withdrawals
+pub fn parse_amount(amount: &Amount) -> anyhow::Result<i128> {
+    Ok(i128::from_str(&amount.value)
+        .map_err(|_| ApiError::InvalidInput(Some(format!("Amount {} is invalid", amount.value))))?)
+}
+
+pub fn format_amount(value: i128, currency: Currency) -> Amount {
+    Amount {
+        value: value.to_string(),
+        currency,
+    }
+}
+
+/// Adds two amounts of the same currency
+pub fn add_amounts(a: &Amount, b: &Amount) -> anyhow::Result<Amount> {
+    if a.currency != b.currency {
+        return Err(ApiError::UnsupportedCurrency(Some(format!(
+            "Can't add {} to {}",
+            b.currency.symbol, a.currency.symbol
+        )))
+        .into());
+    }
+
+    let sum = parse_amount(a)?
+        .checked_add(parse_amount(b)?)
+        .ok_or_else(|| ApiError::AmountOverflow(Some(format!("{} + {}", a.value, b.value))))?;
+    Ok(format_amount(sum, a.currency.clone()))
+}
+
+/// Retrieves a required field from a metadata map, naming the field if it's missing
+pub fn get_required_metadata_field<'a>(
+    map: &'a HashMap<String, serde_json::Value>,
+    key: &'static str,
+) -> Result<&'a serde_json::Value, ApiError> {
+    map.get(key)
+        .ok_or_else(|| ApiError::MissingMetadataField(Some(key.to_string())))
+}
+
 /// Determines which block to pull for the request
 pub async fn get_block_index_from_request(
     server_context: &RosettaContext,
@@ -206,3 +762,456 @@ pub async fn get_block_index_from_request(
 pub fn to_hex_lower<T: LowerHex>(obj: &T) -> String {
     format!("{:x}", obj)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{OperationIdentifier, RotateKey, SignatureType, SigningPayload};
+    use aptos_crypto::{ed25519::Ed25519PrivateKey, PrivateKey};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_public_key_round_trip() {
+        let private_key = Ed25519PrivateKey::try_from([7u8; 32].as_ref()).unwrap();
+        let public_key = private_key.public_key();
+
+        let rosetta_key = encode_public_key(&public_key).unwrap();
+        assert_eq!(CurveType::Edwards25519, rosetta_key.curve_type);
+        assert_eq!(public_key, decode_public_key(&rosetta_key).unwrap());
+    }
+
+    #[test]
+    fn test_decode_public_key_wrong_curve() {
+        let private_key = Ed25519PrivateKey::try_from([7u8; 32].as_ref()).unwrap();
+        let mut rosetta_key = encode_public_key(&private_key.public_key()).unwrap();
+        rosetta_key.curve_type = CurveType::Secp256k1;
+
+        assert!(decode_public_key(&rosetta_key).is_err());
+    }
+
+    #[test]
+    fn test_pre_validate_unsupported_operation_type() {
+        let mut operation =
+            Operation::create_account(0, None, AccountAddress::ONE, AccountAddress::ONE);
+        assert!(pre_validate_operations(&[operation.clone()]).is_ok());
+
+        operation.operation_type = "mint".to_string();
+        assert!(matches!(
+            pre_validate_operations(&[operation]),
+            Err(ApiError::UnsupportedOperationType(Some(ref op_type))) if op_type == "mint"
+        ));
+    }
+
+    #[test]
+    fn test_validate_public_key() {
+        let private_key = Ed25519PrivateKey::try_from([7u8; 32].as_ref()).unwrap();
+        let rosetta_key = encode_public_key(&private_key.public_key()).unwrap();
+        assert!(validate_public_key(&rosetta_key).is_ok());
+
+        let mut short_key = rosetta_key.clone();
+        short_key.hex_bytes.truncate(short_key.hex_bytes.len() - 2);
+        assert!(matches!(
+            validate_public_key(&short_key),
+            Err(ApiError::InvalidHexLength {
+                expected_bytes: 32,
+                got_bytes: 31,
+                field: "public_key"
+            })
+        ));
+
+        let mut not_hex = rosetta_key.clone();
+        not_hex.hex_bytes.replace_range(..2, "zz");
+        assert!(matches!(
+            validate_public_key(&not_hex),
+            Err(ApiError::InvalidPublicKeyFormat(_))
+        ));
+
+        let mut wrong_curve = rosetta_key;
+        wrong_curve.curve_type = CurveType::Secp256k1;
+        assert!(matches!(
+            validate_public_key(&wrong_curve),
+            Err(ApiError::InvalidPublicKeyFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_hex_bytes() {
+        assert_eq!(
+            vec![0xab; 4],
+            validate_hex_bytes("0xabababab", 4, "hash").unwrap()
+        );
+        assert!(matches!(
+            validate_hex_bytes("abab", 4, "hash"),
+            Err(ApiError::InvalidHexLength {
+                expected_bytes: 4,
+                got_bytes: 2,
+                field: "hash"
+            })
+        ));
+        // Bad hex is an encoding problem, not a length problem
+        assert!(matches!(
+            validate_hex_bytes("zzzzzzzz", 4, "hash"),
+            Err(ApiError::DeserializationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_construction_metadata() {
+        let mut metadata = ConstructionMetadataResponse {
+            metadata: crate::types::ConstructionMetadata {
+                sequence_number: 0,
+                max_gas: 10000,
+                gas_price_per_unit: 100,
+                expiry_time_secs: Some(1),
+                min_gas_price_per_unit: None,
+            },
+            suggested_fee: None,
+        };
+        assert!(validate_construction_metadata(&metadata).is_ok());
+
+        // The minimum gas price defaults to zero, so a zero price is fine unless it's higher
+        metadata.metadata.gas_price_per_unit = 0;
+        assert!(validate_construction_metadata(&metadata).is_ok());
+        metadata.metadata.min_gas_price_per_unit = Some(0);
+        assert!(validate_construction_metadata(&metadata).is_ok());
+        metadata.metadata.min_gas_price_per_unit = Some(1);
+        assert!(matches!(
+            validate_construction_metadata(&metadata),
+            Err(ApiError::PartialMetadata(Some(ref details))) if details.ends_with("gas_price_per_unit")
+        ));
+
+        metadata.metadata.gas_price_per_unit = 100;
+        metadata.metadata.expiry_time_secs = None;
+        assert!(matches!(
+            validate_construction_metadata(&metadata),
+            Err(ApiError::PartialMetadata(Some(ref details))) if details.ends_with("expiry_time_secs")
+        ));
+    }
+
+    #[test]
+    fn test_check_minimum_stake() {
+        let minimum_stake = 1_000_000_000;
+        assert!(check_minimum_stake(0, minimum_stake, minimum_stake).is_ok());
+        assert!(matches!(
+            check_minimum_stake(0, 1, minimum_stake),
+            Err(ApiError::InsufficientStake(Some(ref details)))
+                if details.contains("1000000000")
+        ));
+
+        // A top up below the minimum is fine, as long as the pool's total stake reaches it
+        assert!(check_minimum_stake(minimum_stake, 1, minimum_stake).is_ok());
+        assert!(check_minimum_stake(minimum_stake - 1, 1, minimum_stake).is_ok());
+        assert!(check_minimum_stake(minimum_stake - 2, 1, minimum_stake).is_err());
+    }
+
+    #[test]
+    fn test_pre_validate_operation_metadata_too_large() {
+        let operation = Operation::rotate_key(
+            0,
+            None,
+            AccountAddress::ONE,
+            &RotateKey {
+                account: AccountAddress::ONE,
+                current_public_key: vec![],
+                new_public_key: vec![],
+                current_signature: vec![0u8; MAX_OPERATION_METADATA_BYTES],
+                new_signature: vec![],
+            },
+        );
+        assert!(matches!(
+            pre_validate_operations(&[operation]),
+            Err(ApiError::OperationMetadataTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_derive_object_address() {
+        assert_eq!(
+            AccountAddress::from_hex_literal(
+                "0x42c9e315cc59c840c7e1b0cd76765478e668b53d601fa3ba19a9b6573794bec9"
+            )
+            .unwrap(),
+            derive_named_object_address(AccountAddress::ONE, "test_seed")
+        );
+        assert_eq!(
+            AccountAddress::from_hex_literal(
+                "0x4d6d8d610b261cb290c155b6d6dc708090e69e00157059691953d491998e15d8"
+            )
+            .unwrap(),
+            derive_object_address(AccountAddress::from_hex_literal("0xb0b").unwrap(), &[])
+        );
+    }
+
+    #[test]
+    fn test_derive_resource_account_address() {
+        // Same vector as account.move's test_create_resource_account_address
+        assert_eq!(
+            AccountAddress::from_hex_literal(
+                "0x4403707de4f05dadc8266c6431921838590601b1df6a448601d518beb13e97fd"
+            )
+            .unwrap(),
+            derive_resource_account_address(AccountAddress::ONE, &[1])
+        );
+    }
+
+    #[test]
+    fn test_framework_addresses() {
+        let addresses = framework_addresses();
+        assert_eq!(
+            AccountAddress::from_hex_literal("0x3").unwrap(),
+            addresses.token_v1
+        );
+        assert_eq!(
+            AccountAddress::from_hex_literal("0x4").unwrap(),
+            addresses.token_v2
+        );
+        assert!(is_framework_address(AccountAddress::ONE));
+        assert!(is_framework_address(addresses.token_v2));
+        assert!(!is_framework_address(AccountAddress::ZERO));
+        assert!(!is_framework_address(
+            AccountAddress::from_hex_literal("0x2").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_system_addresses() {
+        assert_eq!(
+            AccountAddress::from_hex_literal("0xA550C18").unwrap(),
+            treasury_compliance_address()
+        );
+        assert_eq!(AccountAddress::ONE, core_code_address());
+        assert!(is_treasury_or_system_address(treasury_compliance_address()));
+        assert!(is_treasury_or_system_address(AccountAddress::ZERO));
+        assert!(is_treasury_or_system_address(TOKEN_V1_ADDRESS));
+        assert!(!is_treasury_or_system_address(
+            AccountAddress::from_hex_literal("0xb0b").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_validate_balance_neutral() {
+        let sender = AccountAddress::ONE;
+        let receiver = AccountAddress::from_hex_literal("0x2").unwrap();
+        let withdraw = Operation::withdraw(0, None, sender, native_coin(), 100);
+
+        // A transfer moves the same amount from one account to the other
+        let balanced = vec![
+            withdraw.clone(),
+            Operation::deposit(1, None, receiver, native_coin(), 100),
+            Operation::gas_fee(2, sender, 10, 1),
+        ];
+        assert!(validate_balance_neutral(&balanced).is_ok());
+
+        let mismatched = vec![
+            withdraw,
+            Operation::deposit(1, None, receiver, native_coin(), 99),
+        ];
+        assert!(matches!(
+            validate_balance_neutral(&mismatched),
+            Err(ApiError::InvalidTransferOperations(Some(
+                "net balance is non-zero"
+            )))
+        ));
+
+        // Amounts come from the client, so summing them mustn't overflow
+        let mut deposit = Operation::deposit(0, None, receiver, native_coin(), 0);
+        deposit.amount.as_mut().unwrap().value = i128::MAX.to_string();
+        let overflowing = vec![deposit.clone(), deposit];
+        assert!(matches!(
+            validate_balance_neutral(&overflowing),
+            Err(ApiError::AmountOverflow(_))
+        ));
+    }
+
+    #[test]
+    fn test_pre_validate_duplicate_operation() {
+        let sender = AccountAddress::ONE;
+        let receiver = AccountAddress::from_hex_literal("0x2").unwrap();
+        let withdraw = Operation::withdraw(0, None, sender, native_coin(), 100);
+        let deposit = Operation::deposit(1, None, receiver, native_coin(), 100);
+
+        // All unique indices pass
+        assert!(pre_validate_operations(&[withdraw.clone(), deposit.clone()]).is_ok());
+
+        // Duplicate of the first index
+        let mut duplicate_first = deposit.clone();
+        duplicate_first.operation_identifier.index = 0;
+        assert!(matches!(
+            pre_validate_operations(&[withdraw.clone(), duplicate_first]),
+            Err(ApiError::DuplicateOperation(Some(ref details))) if details == "Operation index 0 is duplicated"
+        ));
+
+        // Duplicate in the middle of the operations
+        let operations = vec![
+            Operation::withdraw(0, None, sender, native_coin(), 100),
+            Operation::deposit(2, None, receiver, native_coin(), 50),
+            Operation::deposit(2, None, receiver, native_coin(), 50),
+            Operation::gas_fee(3, sender, 10, 1),
+        ];
+        assert!(matches!(
+            pre_validate_operations(&operations),
+            Err(ApiError::DuplicateOperation(Some(ref details))) if details == "Operation index 2 is duplicated"
+        ));
+    }
+
+    #[test]
+    fn test_renumber_operations() {
+        let sender = AccountAddress::ONE;
+        let receiver = AccountAddress::from_hex_literal("0x2").unwrap();
+        let withdraw = Operation::withdraw(0, None, sender, native_coin(), 100);
+        let deposit = Operation::deposit(1, None, receiver, native_coin(), 100);
+        let indices = |operations: &[Operation]| -> Vec<u64> {
+            operations
+                .iter()
+                .map(|operation| operation.operation_identifier.index)
+                .collect()
+        };
+
+        // Already normalized operations don't change
+        let normalized = vec![withdraw.clone(), deposit.clone()];
+        assert_eq!(normalized, renumber_operations(normalized.clone()));
+
+        // Gaps are removed, and the order is kept
+        let mut shifted_withdraw = withdraw.clone();
+        shifted_withdraw.operation_identifier.index = 5;
+        let mut shifted_deposit = deposit.clone();
+        shifted_deposit.operation_identifier.index = 3;
+        let renumbered = renumber_operations(vec![shifted_withdraw, shifted_deposit]);
+        assert_eq!(vec![0, 1], indices(&renumbered));
+        assert_eq!(deposit.operation_type, renumbered[0].operation_type);
+        assert_eq!(withdraw.operation_type, renumbered[1].operation_type);
+
+        // A deposit's related withdraw follows the withdraw to its new index
+        let mut related_withdraw = withdraw.clone();
+        related_withdraw.operation_identifier.index = 4;
+        let mut related_deposit = deposit.clone();
+        related_deposit.operation_identifier.index = 9;
+        related_deposit.related_operations = Some(vec![OperationIdentifier {
+            index: 4,
+            network_index: None,
+        }]);
+        let renumbered = renumber_operations(vec![related_deposit, related_withdraw]);
+        assert_eq!(vec![0, 1], indices(&renumbered));
+        assert_eq!(
+            Some(vec![OperationIdentifier {
+                index: 0,
+                network_index: None,
+            }]),
+            renumbered[1].related_operations
+        );
+
+        let mut single = withdraw.clone();
+        single.operation_identifier.index = 7;
+        assert_eq!(vec![withdraw], renumber_operations(vec![single]));
+    }
+
+    #[test]
+    fn test_check_conflicting_signers() {
+        let signature = |private_key: &Ed25519PrivateKey| Signature {
+            signing_payload: SigningPayload {
+                address: None,
+                account_identifier: None,
+                hex_bytes: String::new(),
+                signature_type: None,
+            },
+            public_key: encode_public_key(&private_key.public_key()).unwrap(),
+            signature_type: SignatureType::Ed25519,
+            hex_bytes: String::new(),
+        };
+        let sender = Ed25519PrivateKey::try_from([7u8; 32].as_ref()).unwrap();
+        let secondary = Ed25519PrivateKey::try_from([1u8; 32].as_ref()).unwrap();
+
+        assert!(check_conflicting_signers(&[signature(&sender), signature(&secondary)]).is_ok());
+        assert!(matches!(
+            check_conflicting_signers(&[signature(&sender), signature(&sender)]),
+            Err(ApiError::ConflictingSigners(_))
+        ));
+    }
+
+    #[test]
+    fn test_group_operations_by_sender() {
+        let sender = AccountAddress::ONE;
+        let other_sender = AccountAddress::from_hex_literal("0x2").unwrap();
+        let receiver = AccountAddress::from_hex_literal("0x3").unwrap();
+        let transfer = vec![
+            Operation::withdraw(0, None, sender, native_coin(), 100),
+            Operation::deposit(1, None, receiver, native_coin(), 100),
+        ];
+
+        // Deposits don't count as being sent by the receiver
+        let groups = group_operations_by_sender(&transfer);
+        assert_eq!(1, groups.len());
+        assert_eq!(1, groups[&sender].len());
+        assert_eq!(
+            sender,
+            validate_single_sender_operations(&transfer).unwrap()
+        );
+
+        // Created accounts are sent by the creator
+        let create_account = vec![Operation::create_account(0, None, receiver, sender)];
+        assert_eq!(
+            sender,
+            validate_single_sender_operations(&create_account).unwrap()
+        );
+
+        let multiple_senders = vec![
+            Operation::withdraw(0, None, sender, native_coin(), 100),
+            Operation::withdraw(1, None, other_sender, native_coin(), 100),
+        ];
+        assert_eq!(2, group_operations_by_sender(&multiple_senders).len());
+        assert!(matches!(
+            validate_single_sender_operations(&multiple_senders),
+            Err(ApiError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_amounts() {
+        let sum = add_amounts(
+            &format_amount(-5, native_coin()),
+            &format_amount(12, native_coin()),
+        )
+        .unwrap();
+        assert_eq!(sum, format_amount(7, native_coin()));
+
+        let overflow = add_amounts(
+            &format_amount(i128::MAX, native_coin()),
+            &format_amount(1, native_coin()),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            overflow.downcast_ref::<ApiError>(),
+            Some(ApiError::AmountOverflow(_))
+        ));
+
+        let other_coin = Currency {
+            symbol: "USDC".to_string(),
+            decimals: 6,
+            metadata: None,
+        };
+        let mismatch = add_amounts(
+            &format_amount(1, native_coin()),
+            &format_amount(1, other_coin),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            mismatch.downcast_ref::<ApiError>(),
+            Some(ApiError::UnsupportedCurrency(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_required_metadata_field() {
+        let mut map = HashMap::new();
+        map.insert("sender".to_string(), serde_json::Value::from("0x1"));
+
+        assert_eq!(
+            get_required_metadata_field(&map, "sender").unwrap(),
+            &serde_json::Value::from("0x1")
+        );
+        assert!(matches!(
+            get_required_metadata_field(&map, "operator"),
+            Err(ApiError::MissingMetadataField(Some(key))) if key == "operator"
+        ));
+    }
+}
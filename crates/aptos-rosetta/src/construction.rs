@@ -26,35 +26,40 @@
 
 use crate::{
     common::{
-        check_network, decode_bcs, decode_key, encode_bcs, get_account, handle_request,
-        is_native_coin, native_coin, to_hex_lower, with_context,
+        check_conflicting_signers, check_minimum_stake, check_network, decode_bcs,
+        decode_ed25519_public_key, decode_key, encode_bcs, get_account, get_gas_schedule_entries,
+        get_minimum_stake, get_pool_stake, handle_request, is_native_coin, native_coin,
+        pre_validate_operations, to_hex_lower, validate_hex_bytes, validate_public_key,
+        with_context,
     },
-    error::{ApiError, ApiResult},
+    error::{ApiError, ApiResult, FieldError},
     types::{InternalOperation, *},
     RosettaContext,
 };
 use aptos_crypto::{
-    ed25519::{Ed25519PublicKey, Ed25519Signature},
+    ed25519::{Ed25519Signature, ED25519_SIGNATURE_LENGTH},
     hash::CryptoHash,
+    multi_ed25519::{MultiEd25519PublicKey, MultiEd25519Signature},
     signing_message,
 };
 use aptos_logger::debug;
 use aptos_sdk::{
     move_types::{
         identifier::Identifier,
-        language_storage::{StructTag, TypeTag},
+        language_storage::{ModuleId, StructTag, TypeTag},
     },
     transaction_builder::TransactionFactory,
 };
 use aptos_types::{
     account_address::AccountAddress,
     transaction::{
-        authenticator::AuthenticationKey, RawTransaction, SignedTransaction,
+        authenticator::AuthenticationKey, EntryFunction, RawTransaction, SignedTransaction,
         Transaction::UserTransaction, TransactionPayload,
     },
 };
 use cached_packages::aptos_stdlib;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 use warp::Filter;
 
 pub fn combine_route(
@@ -152,9 +157,20 @@ async fn construction_combine(
     let unsigned_txn: RawTransaction =
         decode_bcs(&request.unsigned_transaction, "UnsignedTransaction")?;
 
+    check_conflicting_signers(&request.signatures)?;
+
+    if let Some(ref multi_ed25519_public_key) = request.multi_ed25519_public_key {
+        return combine_multi_ed25519(unsigned_txn, multi_ed25519_public_key, &request.signatures);
+    }
+
     // Single signer only supported for now
     // TODO: Support multi-agent / multi-signer?
-    if request.signatures.len() != 1 {
+    if request.signatures.is_empty() {
+        return Err(ApiError::SignatureCountMismatch {
+            expected: 1,
+            got: 0,
+        });
+    } else if request.signatures.len() != 1 {
         return Err(ApiError::UnsupportedSignatureCount(Some(
             request.signatures.len(),
         )));
@@ -168,8 +184,8 @@ async fn construction_combine(
         return Err(ApiError::InvalidSignatureType);
     }
 
-    let public_key: Ed25519PublicKey =
-        decode_key(&signature.public_key.hex_bytes, "Ed25519PublicKey")?;
+    let public_key = decode_ed25519_public_key(&signature.public_key.hex_bytes, "public_key")?;
+    validate_hex_bytes(&signature.hex_bytes, ED25519_SIGNATURE_LENGTH, "signature")?;
     let signature: Ed25519Signature = decode_key(&signature.hex_bytes, "Ed25519Signature")?;
 
     let signed_txn = SignedTransaction::new(unsigned_txn, public_key, signature);
@@ -179,6 +195,57 @@ async fn construction_combine(
     })
 }
 
+/// Combines signatures from the keys of a MultiEd25519 signer into a single authenticator
+///
+/// Each signature must be from one of the keys in the public key, and there must be enough of
+/// them to meet the threshold
+fn combine_multi_ed25519(
+    unsigned_txn: RawTransaction,
+    multi_ed25519_public_key: &str,
+    signatures: &[Signature],
+) -> ApiResult<ConstructionCombineResponse> {
+    let public_key: MultiEd25519PublicKey =
+        decode_key(multi_ed25519_public_key, "MultiEd25519PublicKey")?;
+
+    let mut indexed_signatures = Vec::with_capacity(signatures.len());
+    for signature in signatures {
+        if signature.signature_type != SignatureType::Ed25519
+            || signature.public_key.curve_type != CurveType::Edwards25519
+        {
+            return Err(ApiError::InvalidSignatureType);
+        }
+
+        let signer = decode_ed25519_public_key(&signature.public_key.hex_bytes, "public_key")?;
+        let index = public_key
+            .public_keys()
+            .iter()
+            .position(|key| *key == signer)
+            .ok_or_else(|| {
+                ApiError::InvalidInput(Some(format!(
+                    "Public key {} isn't part of the MultiEd25519 public key",
+                    signer
+                )))
+            })?;
+        validate_hex_bytes(&signature.hex_bytes, ED25519_SIGNATURE_LENGTH, "signature")?;
+        let signature: Ed25519Signature = decode_key(&signature.hex_bytes, "Ed25519Signature")?;
+        // There are at most 32 keys, so the index always fits
+        indexed_signatures.push((signature, index as u8));
+    }
+
+    if indexed_signatures.len() < usize::from(*public_key.threshold()) {
+        return Err(ApiError::UnsupportedSignatureCount(Some(
+            indexed_signatures.len(),
+        )));
+    }
+    let signature = MultiEd25519Signature::new(indexed_signatures)
+        .map_err(|err| ApiError::InvalidInput(Some(err.to_string())))?;
+    let signed_txn = SignedTransaction::new_multisig(unsigned_txn, public_key, signature);
+
+    Ok(ConstructionCombineResponse {
+        signed_transaction: encode_bcs(&signed_txn)?,
+    })
+}
+
 /// Construction derive command (OFFLINE)
 ///
 /// Derive account address from Public key
@@ -193,8 +260,8 @@ async fn construction_derive(
     debug!("/construction/derive {:?}", request);
     check_network(request.network_identifier, &server_context)?;
 
-    let public_key: Ed25519PublicKey =
-        decode_key(&request.public_key.hex_bytes, "Ed25519PublicKey")?;
+    validate_public_key(&request.public_key)?;
+    let public_key = decode_ed25519_public_key(&request.public_key.hex_bytes, "public_key")?;
     let address = to_hex_lower(&AuthenticationKey::ed25519(&public_key).derived_address());
 
     let account_identifier = Some(AccountIdentifier {
@@ -225,6 +292,9 @@ async fn construction_hash(
     })
 }
 
+/// The APT `CoinStore`, with the type's angle brackets URL encoded
+const APTOS_COIN_STORE: &str = "0x1::coin::CoinStore%3C0x1::aptos_coin::AptosCoin%3E";
+
 /// Construction metadata command
 ///
 /// Retrieve sequence number for submitting transactions
@@ -246,6 +316,43 @@ async fn construction_metadata(
         return Err(ApiError::ChainIdMismatch);
     }
 
+    // Creating an account that exists would abort onchain, and still charge gas
+    if let InternalOperation::CreateAccount(ref create_account) = request.options.internal_operation
+    {
+        if rest_client
+            .get_account(create_account.new_account)
+            .await
+            .is_ok()
+        {
+            return Err(ApiError::AccountAlreadyExists(Some(format!(
+                "Account {} already exists",
+                create_account.new_account
+            ))));
+        }
+    }
+
+    // Withdrawing APT requires the sender to have registered it, the sender exists at this point
+    if let InternalOperation::Transfer(ref transfer) = request.options.internal_operation {
+        if rest_client
+            .get_account_resource(transfer.sender, APTOS_COIN_STORE)
+            .await?
+            .into_inner()
+            .is_none()
+        {
+            return Err(ApiError::aptos_coin_not_registered(transfer.sender, true));
+        }
+    }
+
+    // A pool with less than the minimum stake can't be used to join the validator set
+    if let InternalOperation::AddValidatorStake(ref add_stake) = request.options.internal_operation
+    {
+        check_minimum_stake(
+            get_pool_stake(&rest_client, add_stake.owner).await?,
+            add_stake.amount,
+            get_minimum_stake(&rest_client).await?,
+        )?;
+    }
+
     let sequence_number = if let Some(sequence_number) = request.options.sequence_number {
         sequence_number
     } else {
@@ -253,17 +360,38 @@ async fn construction_metadata(
         response.inner().sequence_number
     };
 
+    // The fee can be at most the max gas at the given gas price
+    let suggested_fee = Amount {
+        value: request
+            .options
+            .max_gas
+            .saturating_mul(request.options.gas_price_per_unit)
+            .to_string(),
+        currency: native_coin(),
+    };
+
     Ok(ConstructionMetadataResponse {
         metadata: ConstructionMetadata {
             sequence_number,
             max_gas: request.options.max_gas,
             gas_price_per_unit: request.options.gas_price_per_unit,
             expiry_time_secs: request.options.expiry_time_secs,
+            min_gas_price_per_unit: get_min_gas_price_per_unit(&rest_client).await,
         },
-        suggested_fee: None,
+        suggested_fee: Some(vec![suggested_fee]),
     })
 }
 
+/// Looks up the lowest gas price the chain accepts, from the onchain gas schedule
+///
+/// The minimum is only informational, so any failure to look it up is `None`
+async fn get_min_gas_price_per_unit(rest_client: &aptos_rest_client::Client) -> Option<u64> {
+    get_gas_schedule_entries(rest_client, None)
+        .await
+        .ok()?
+        .remove("txn.min_price_per_gas_unit")
+}
+
 /// Construction parse command (OFFLINE)
 ///
 /// Parses operations from a transaction, used for verifying transaction construction
@@ -293,9 +421,21 @@ async fn construction_parse(
         (None, unsigned_txn)
     };
     let sender = unsigned_txn.sender();
+    let operations = parse_operations(sender, unsigned_txn.into_payload())?;
 
+    Ok(ConstructionParseResponse {
+        operations,
+        account_identifier_signers,
+    })
+}
+
+/// Converts a transaction payload back into the operations that would have built it
+pub(crate) fn parse_operations(
+    sender: AccountAddress,
+    payload: TransactionPayload,
+) -> ApiResult<Vec<Operation>> {
     // This is messy, but all we can do
-    let operations = match unsigned_txn.into_payload() {
+    match payload {
         TransactionPayload::EntryFunction(inner) => {
             let (module, function_name, type_args, args) = inner.into_inner();
 
@@ -304,43 +444,56 @@ async fn construction_parse(
                 && coin_module_identifier() == module_name
                 && transfer_function_identifier() == function_name
             {
-                parse_transfer_operation(sender, &type_args, &args)?
+                parse_transfer_operation(sender, &type_args, &args)
             } else if AccountAddress::ONE == *module.address()
                 && account_module_identifier() == module_name
                 && transfer_function_identifier() == function_name
             {
-                parse_account_transfer_operation(sender, &type_args, &args)?
+                parse_account_transfer_operation(sender, &type_args, &args)
             } else if AccountAddress::ONE == *module.address()
                 && account_module_identifier() == module_name
                 && create_account_function_identifier() == function_name
             {
-                parse_create_account_operation(sender, &type_args, &args)?
+                parse_create_account_operation(sender, &type_args, &args)
             } else if AccountAddress::ONE == *module.address()
                 && stake_module_identifier() == module_name
                 && set_operator_function_identifier() == function_name
             {
-                parse_set_operator_operation(sender, &type_args, &args)?
+                parse_set_operator_operation(sender, &type_args, &args)
+            } else if AccountAddress::ONE == *module.address()
+                && stake_module_identifier() == module_name
+                && add_stake_function_identifier() == function_name
+            {
+                parse_add_validator_stake_operation(sender, &type_args, &args)
+            } else if AccountAddress::ONE == *module.address()
+                && stake_module_identifier() == module_name
+                && unlock_function_identifier() == function_name
+            {
+                parse_unlock_stake_operation(sender, &type_args, &args)
+            } else if AccountAddress::ONE == *module.address()
+                && stake_module_identifier() == module_name
+                && withdraw_function_identifier() == function_name
+            {
+                parse_withdraw_inactive_stake_operation(sender, &type_args, &args)
+            } else if AccountAddress::ONE == *module.address()
+                && account_module_identifier() == module_name
+                && rotate_authentication_key_ed25519_function_identifier() == function_name
+            {
+                parse_rotate_key_operation(sender, &type_args, &args)
             } else {
-                return Err(ApiError::TransactionParseError(Some(format!(
+                Err(ApiError::TransactionParseError(Some(format!(
                     "Unsupported entry function type {:x}::{}::{}",
                     module.address(),
                     module_name,
                     function_name
-                ))));
+                ))))
             }
         }
-        payload => {
-            return Err(ApiError::TransactionParseError(Some(format!(
-                "Unsupported transaction payload type {:?}",
-                payload
-            ))))
-        }
-    };
-
-    Ok(ConstructionParseResponse {
-        operations,
-        account_identifier_signers,
-    })
+        payload => Err(ApiError::TransactionParseError(Some(format!(
+            "Unsupported transaction payload type {:?}",
+            payload
+        )))),
+    }
 }
 
 fn parse_create_account_operation(
@@ -484,6 +637,110 @@ fn parse_set_operator_operation(
     }
 }
 
+fn parse_rotate_key_operation(
+    sender: AccountAddress,
+    type_args: &[TypeTag],
+    args: &[Vec<u8>],
+) -> ApiResult<Vec<Operation>> {
+    // There are no typeargs for rotating the key
+    if !type_args.is_empty() {
+        return Err(ApiError::TransactionParseError(Some(format!(
+            "Rotate key should not have type arguments: {:?}",
+            type_args
+        ))));
+    }
+
+    if let [current_signature, new_signature, current_public_key, new_public_key] = args {
+        let rotate_key = RotateKey {
+            account: sender,
+            current_public_key: bcs::from_bytes(current_public_key)?,
+            new_public_key: bcs::from_bytes(new_public_key)?,
+            current_signature: bcs::from_bytes(current_signature)?,
+            new_signature: bcs::from_bytes(new_signature)?,
+        };
+
+        Ok(vec![Operation::rotate_key(0, None, sender, &rotate_key)])
+    } else {
+        Err(ApiError::InvalidOperations)
+    }
+}
+
+fn parse_add_validator_stake_operation(
+    sender: AccountAddress,
+    type_args: &[TypeTag],
+    args: &[Vec<u8>],
+) -> ApiResult<Vec<Operation>> {
+    // There are no typeargs for add stake
+    if !type_args.is_empty() {
+        return Err(ApiError::TransactionParseError(Some(format!(
+            "Add stake should not have type arguments: {:?}",
+            type_args
+        ))));
+    }
+
+    // Add stake
+    if let Some(encoded_amount) = args.first() {
+        let amount: u64 = bcs::from_bytes(encoded_amount)?;
+
+        Ok(vec![Operation::add_validator_stake(
+            0, None, sender, amount,
+        )])
+    } else {
+        Err(ApiError::InvalidOperations)
+    }
+}
+
+fn parse_unlock_stake_operation(
+    sender: AccountAddress,
+    type_args: &[TypeTag],
+    args: &[Vec<u8>],
+) -> ApiResult<Vec<Operation>> {
+    // There are no typeargs for unlock
+    if !type_args.is_empty() {
+        return Err(ApiError::TransactionParseError(Some(format!(
+            "Unlock stake should not have type arguments: {:?}",
+            type_args
+        ))));
+    }
+
+    if let Some(encoded_amount) = args.first() {
+        let amount: u64 = bcs::from_bytes(encoded_amount)?;
+
+        Ok(vec![Operation::unlock_stake(0, None, sender, amount)])
+    } else {
+        Err(ApiError::InvalidOperations)
+    }
+}
+
+fn parse_withdraw_inactive_stake_operation(
+    sender: AccountAddress,
+    type_args: &[TypeTag],
+    args: &[Vec<u8>],
+) -> ApiResult<Vec<Operation>> {
+    // There are no typeargs for withdraw
+    if !type_args.is_empty() {
+        return Err(ApiError::TransactionParseError(Some(format!(
+            "Withdraw stake should not have type arguments: {:?}",
+            type_args
+        ))));
+    }
+
+    if let Some(encoded_amount) = args.first() {
+        let amount: u64 = bcs::from_bytes(encoded_amount)?;
+        let amount = if amount == u64::MAX {
+            None
+        } else {
+            Some(amount)
+        };
+
+        Ok(vec![Operation::withdraw_inactive_stake(
+            0, None, sender, amount,
+        )])
+    } else {
+        Err(ApiError::InvalidOperations)
+    }
+}
+
 /// Construction payloads command (OFFLINE)
 ///
 /// Constructs payloads for given known operations
@@ -497,6 +754,7 @@ async fn construction_payloads(
     check_network(request.network_identifier, &server_context)?;
 
     // Retrieve the real operation we're doing
+    pre_validate_operations(&request.operations)?;
     let operation = InternalOperation::extract(&request.operations)?;
     let metadata = if let Some(ref metadata) = request.metadata {
         metadata
@@ -521,6 +779,28 @@ async fn construction_payloads(
             aptos_stdlib::stake_set_operator(set_operator.operator),
             set_operator.owner,
         ),
+        InternalOperation::AddValidatorStake(add_stake) => (
+            aptos_stdlib::stake_add_stake(add_stake.amount),
+            add_stake.owner,
+        ),
+        InternalOperation::UnlockStake(unlock_stake) => (
+            aptos_stdlib::stake_unlock(unlock_stake.amount),
+            unlock_stake.owner,
+        ),
+        // The framework caps withdrawals at the inactive stake, so the max withdraws all of it
+        InternalOperation::WithdrawInactiveStake(withdraw) => (
+            aptos_stdlib::stake_withdraw(withdraw.amount.unwrap_or(u64::MAX)),
+            withdraw.owner,
+        ),
+        InternalOperation::RotateKey(rotate_key) => (
+            aptos_stdlib::account_rotate_authentication_key_ed25519(
+                rotate_key.current_signature,
+                rotate_key.new_signature,
+                rotate_key.current_public_key,
+                rotate_key.new_public_key,
+            ),
+            rotate_key.account,
+        ),
     };
 
     // Build the transaction and make it ready for signing
@@ -528,6 +808,17 @@ async fn construction_payloads(
         .with_gas_unit_price(metadata.gas_price_per_unit)
         .with_max_gas_amount(metadata.max_gas);
     if let Some(expiry_time_secs) = metadata.expiry_time_secs {
+        // A transaction that's already expired can never be committed
+        let now_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| ApiError::InternalError(Some(err.to_string())))?
+            .as_secs();
+        if expiry_time_secs <= now_secs {
+            return Err(ApiError::InvalidExpiryTime(Some(format!(
+                "Expiration time {} is not after the current time {}",
+                expiry_time_secs, now_secs
+            ))));
+        }
         transaction_factory =
             transaction_factory.with_transaction_expiration_time(expiry_time_secs);
     }
@@ -569,29 +860,32 @@ async fn construction_preprocess(
     debug!("/construction/preprocess {:?}", request);
     check_network(request.network_identifier, &server_context)?;
 
-    // Ensure that the max fee is only in the native coin
-    let max_gas = if let Some(max_fees) = request.max_fee {
-        if max_fees.len() != 1 {
-            return Err(ApiError::InvalidMaxGasFees);
-        }
-        let max_fee = max_fees.first().unwrap();
-        is_native_coin(&max_fee.currency)?;
-        u64::from_str(&max_fee.value)?
-    } else {
-        DEFAULT_MAX_GAS_PRICE
-    };
-
-    // Let's not accept fractions, as we don't support it
-    let gas_price_per_unit = if let Some(fee_multiplier) = request.suggested_fee_multiplier {
-        if fee_multiplier != (fee_multiplier as u32) as f64 {
-            return Err(ApiError::InvalidGasMultiplier);
+    // Report every invalid fee field at once, so they can all be fixed together
+    let (max_gas, gas_price_per_unit) = match (
+        parse_max_fee(request.max_fee.as_deref()),
+        parse_fee_multiplier(request.suggested_fee_multiplier),
+    ) {
+        (Ok(max_gas), Ok(gas_price_per_unit)) => (max_gas, gas_price_per_unit),
+        (Err(max_fee_error), Err(multiplier_error)) => {
+            return Err(ApiError::FieldValidationFailed(vec![
+                FieldError::new(
+                    "max_fee",
+                    &max_fee_error,
+                    serde_json::to_value(&request.max_fee).ok(),
+                ),
+                FieldError::new(
+                    "suggested_fee_multiplier",
+                    &multiplier_error,
+                    request
+                        .suggested_fee_multiplier
+                        .map(serde_json::Value::from),
+                ),
+            ]))
         }
-
-        fee_multiplier as u64
-    } else {
-        DEFAULT_GAS_PRICE_PER_UNIT
+        (Err(err), _) | (_, Err(err)) => return Err(err),
     };
 
+    pre_validate_operations(&request.operations)?;
     let internal_operation = InternalOperation::extract(&request.operations)?;
     let required_public_keys = vec![internal_operation.sender().into()];
 
@@ -613,6 +907,34 @@ async fn construction_preprocess(
     })
 }
 
+/// Parses the max gas from the max fee, which must only be in the native coin
+fn parse_max_fee(max_fees: Option<&[Amount]>) -> ApiResult<u64> {
+    if let Some(max_fees) = max_fees {
+        if max_fees.len() != 1 {
+            return Err(ApiError::InvalidMaxGasFees);
+        }
+        let max_fee = max_fees.first().unwrap();
+        is_native_coin(&max_fee.currency)?;
+        Ok(u64::from_str(&max_fee.value)?)
+    } else {
+        Ok(DEFAULT_MAX_GAS_PRICE)
+    }
+}
+
+/// Parses the gas price from the fee multiplier
+fn parse_fee_multiplier(fee_multiplier: Option<f64>) -> ApiResult<u64> {
+    if let Some(fee_multiplier) = fee_multiplier {
+        // Let's not accept fractions, as we don't support it
+        if fee_multiplier != (fee_multiplier as u32) as f64 {
+            return Err(ApiError::InvalidGasMultiplier);
+        }
+
+        Ok(fee_multiplier as u64)
+    } else {
+        Ok(DEFAULT_GAS_PRICE_PER_UNIT)
+    }
+}
+
 /// Construction submit command (OFFLINE)
 ///
 /// Submits a transaction to the blockchain
@@ -1,14 +1,32 @@
 // Copyright (c) Aptos
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{types, types::ErrorDetails};
-use aptos_rest_client::aptos_api_types::AptosErrorCode;
+use crate::{
+    types,
+    types::{ErrorDetails, RecoveryAction, RecoveryHint},
+};
+use aptos_rest_client::aptos_api_types::{
+    mime_types::{BCS, JSON},
+    AptosErrorCode,
+};
 use aptos_rest_client::error::RestError;
+use aptos_types::account_address::AccountAddress;
+use aptos_types::vm_status::StatusCode as VmStatusCode;
 use hex::FromHexError;
 use move_deps::move_core_types::account_address::AccountAddressParseError;
 use serde::{Deserialize, Serialize};
 use std::fmt::Formatter;
-use warp::{http::StatusCode, reply::Reply};
+use warp::{http::StatusCode, hyper::body::Bytes, reply::Reply};
+
+/// End of the details of [`ApiError::AptosCoinNotRegistered`] when the account doesn't exist
+const ACCOUNT_DOES_NOT_EXIST: &str = "does not exist, so has not registered APT";
+
+/// Parts of the REST API's messages when a requested version hasn't been committed yet, from
+/// nodes that report it as [`AptosErrorCode::VersionNotFound`]
+const VERSION_AHEAD_OF_LEDGER: [&str; 2] = ["Ledger version not found by", "ahead of"];
+
+/// `425 Too Early`, which doesn't have a constant in [`StatusCode`]
+const TOO_EARLY: u16 = 425;
 
 pub type ApiResult<T> = Result<T, ApiError>;
 
@@ -27,9 +45,54 @@ pub enum ApiError {
     MissingPayloadMetadata,
     UnsupportedCurrency(Option<String>),
     UnsupportedSignatureCount(Option<usize>),
-    NodeIsOffline,
+    NodeIsOffline(Option<String>),
     TransactionParseError(Option<String>),
+    TransactionExpired(Option<String>),
+    InvalidExpiryTime(Option<String>),
     InternalError(Option<String>),
+    MaxRetriesExceeded {
+        attempts: u32,
+        last_error: Box<ApiError>,
+    },
+    UnsupportedOperationType(Option<String>),
+    EpochChangeInProgress(Option<String>),
+    InsufficientGasBalance(Option<String>),
+    SignatureCountMismatch {
+        expected: usize,
+        got: usize,
+    },
+    DuplicateOperation(Option<String>),
+    IncompatibleApiVersion(Option<String>),
+    ConflictingSigners(Option<String>),
+    MaximumBlockSizeExceeded(Option<String>),
+    FieldValidationFailed(Vec<FieldError>),
+    TooManyRequests {
+        /// Endpoint that was rate limited, which isn't known when deserialized
+        #[serde(skip_deserializing)]
+        endpoint: &'static str,
+        retry_after_secs: Option<u64>,
+        limit: Option<u32>,
+        window_secs: Option<u32>,
+    },
+    AccountAlreadyExists(Option<String>),
+    GasPriceUnderMinimum(Option<String>),
+    AmountOverflow(Option<String>),
+    MissingMetadataField(Option<String>),
+    TestModeOnly,
+    OperationMetadataTooLarge(Option<String>),
+    InvalidPublicKeyFormat(Option<String>),
+    ConsensusFailure(Option<String>),
+    InsufficientStake(Option<String>),
+    AptosCoinNotRegistered(Option<String>),
+    InvalidHexLength {
+        expected_bytes: usize,
+        got_bytes: usize,
+        /// Field that was the wrong length, which isn't known when deserialized
+        #[serde(skip_deserializing)]
+        field: &'static str,
+    },
+    LedgerVersionTooHigh(Option<String>),
+    PartialMetadata(Option<String>),
 
     // Below here are codes directly from the REST API
     AccountNotFound(Option<String>),
@@ -49,13 +112,42 @@ pub enum ApiError {
     MempoolIsFull(Option<String>),
 }
 
+/// A single invalid field in a request
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FieldError {
+    /// Name of the field in the request
+    pub field: String,
+    /// Why the field is invalid
+    pub message: String,
+    /// The invalid value, if it can be shown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+}
+
+impl FieldError {
+    pub fn new(field: &str, error: &ApiError, value: Option<serde_json::Value>) -> FieldError {
+        FieldError {
+            field: field.to_string(),
+            message: error.message(),
+            value,
+        }
+    }
+}
+
 impl std::fmt::Display for ApiError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
 
-impl std::error::Error for ApiError {}
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::MaxRetriesExceeded { last_error, .. } => Some(&**last_error),
+            _ => None,
+        }
+    }
+}
 
 impl ApiError {
     pub fn all() -> Vec<ApiError> {
@@ -74,9 +166,50 @@ impl ApiError {
             MissingPayloadMetadata,
             UnsupportedCurrency(None),
             UnsupportedSignatureCount(None),
-            NodeIsOffline,
+            NodeIsOffline(None),
             TransactionParseError(None),
+            TransactionExpired(None),
+            InvalidExpiryTime(None),
             InternalError(None),
+            MaxRetriesExceeded {
+                attempts: 0,
+                last_error: Box::new(InternalError(None)),
+            },
+            UnsupportedOperationType(None),
+            EpochChangeInProgress(None),
+            InsufficientGasBalance(None),
+            SignatureCountMismatch {
+                expected: 0,
+                got: 0,
+            },
+            DuplicateOperation(None),
+            IncompatibleApiVersion(None),
+            ConflictingSigners(None),
+            MaximumBlockSizeExceeded(None),
+            FieldValidationFailed(Vec::new()),
+            TooManyRequests {
+                endpoint: "",
+                retry_after_secs: None,
+                limit: None,
+                window_secs: None,
+            },
+            AccountAlreadyExists(None),
+            GasPriceUnderMinimum(None),
+            AmountOverflow(None),
+            MissingMetadataField(None),
+            TestModeOnly,
+            OperationMetadataTooLarge(None),
+            InvalidPublicKeyFormat(None),
+            ConsensusFailure(None),
+            InsufficientStake(None),
+            AptosCoinNotRegistered(None),
+            InvalidHexLength {
+                expected_bytes: 0,
+                got_bytes: 0,
+                field: "",
+            },
+            LedgerVersionTooHigh(None),
+            PartialMetadata(None),
             AccountNotFound(None),
             ResourceNotFound(None),
             ModuleNotFound(None),
@@ -111,8 +244,34 @@ impl ApiError {
             MissingPayloadMetadata => 10,
             UnsupportedCurrency(_) => 11,
             UnsupportedSignatureCount(_) => 12,
-            NodeIsOffline => 13,
+            NodeIsOffline(_) => 13,
             TransactionParseError(_) => 14,
+            TransactionExpired(_) => 15,
+            InvalidExpiryTime(_) => 16,
+            MaxRetriesExceeded { .. } => 51,
+            UnsupportedOperationType(_) => 52,
+            EpochChangeInProgress(_) => 53,
+            InsufficientGasBalance(_) => 54,
+            SignatureCountMismatch { .. } => 55,
+            DuplicateOperation(_) => 56,
+            IncompatibleApiVersion(_) => 57,
+            ConflictingSigners(_) => 59,
+            MaximumBlockSizeExceeded(_) => 60,
+            FieldValidationFailed(_) => 61,
+            TooManyRequests { .. } => 62,
+            AccountAlreadyExists(_) => 63,
+            GasPriceUnderMinimum(_) => 64,
+            AmountOverflow(_) => 75,
+            MissingMetadataField(_) => 65,
+            TestModeOnly => 66,
+            OperationMetadataTooLarge(_) => 67,
+            InvalidPublicKeyFormat(_) => 68,
+            ConsensusFailure(_) => 69,
+            InsufficientStake(_) => 70,
+            AptosCoinNotRegistered(_) => 71,
+            InvalidHexLength { .. } => 72,
+            LedgerVersionTooHigh(_) => 73,
+            PartialMetadata(_) => 74,
             InternalError(_) => AptosErrorCode::InternalError.as_u32(),
             AccountNotFound(_) => AptosErrorCode::AccountNotFound.as_u32(),
             ResourceNotFound(_) => AptosErrorCode::ResourceNotFound.as_u32(),
@@ -136,7 +295,13 @@ impl ApiError {
         use ApiError::*;
         matches!(
             self,
-            AccountNotFound(_) | BlockNotFound(_) | MempoolIsFull(_)
+            AccountNotFound(_)
+                | BlockNotFound(_)
+                | MempoolIsFull(_)
+                | EpochChangeInProgress(_)
+                | TooManyRequests { .. }
+                | ConsensusFailure(_)
+                | LedgerVersionTooHigh(_)
         )
     }
 
@@ -153,7 +318,18 @@ impl ApiError {
             | TableItemNotFound(_) => StatusCode::NOT_FOUND,
             MempoolIsFull(_) => StatusCode::INSUFFICIENT_STORAGE,
             BlockPruned(_) | VersionPruned(_) => StatusCode::GONE,
-            NodeIsOffline => StatusCode::METHOD_NOT_ALLOWED,
+            NodeIsOffline(_) => StatusCode::METHOD_NOT_ALLOWED,
+            UnsupportedOperationType(_) => StatusCode::NOT_IMPLEMENTED,
+            EpochChangeInProgress(_) | ConsensusFailure(_) => StatusCode::SERVICE_UNAVAILABLE,
+            IncompatibleApiVersion(_) => StatusCode::UPGRADE_REQUIRED,
+            MaximumBlockSizeExceeded(_) | OperationMetadataTooLarge(_) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AccountAlreadyExists(_) => StatusCode::CONFLICT,
+            TestModeOnly => StatusCode::FORBIDDEN,
+            AptosCoinNotRegistered(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            LedgerVersionTooHigh(_) => StatusCode::from_u16(TOO_EARLY).unwrap(),
             _ => StatusCode::BAD_REQUEST,
         }
     }
@@ -176,10 +352,55 @@ impl ApiError {
             ApiError::MissingPayloadMetadata => "Payload metadata is missing",
             ApiError::UnsupportedCurrency(_) => "Currency is unsupported",
             ApiError::UnsupportedSignatureCount(_) => "Number of signatures is not supported",
-            ApiError::NodeIsOffline => "This API is unavailable for the node because he's offline",
+            ApiError::NodeIsOffline(_) => "This API is unavailable for the node because he's offline",
             ApiError::BlockNotFound(_) => "Block is missing events",
             ApiError::TransactionParseError(_) => "Transaction failed to parse",
+            ApiError::TransactionExpired(_) => {
+                "Transaction expired.  Please create a new transaction with a later expiration time"
+            }
+            ApiError::InvalidExpiryTime(_) => "Expiration time is in the past",
             ApiError::InternalError(_) => "Internal error",
+            ApiError::MaxRetriesExceeded {
+                attempts,
+                last_error,
+            } => {
+                return format!(
+                    "Max retries exceeded after {} attempts: {}",
+                    attempts,
+                    last_error.message()
+                )
+            }
+            ApiError::UnsupportedOperationType(_) => {
+                "Operation type is not supported by this server"
+            }
+            ApiError::EpochChangeInProgress(_) => {
+                "Epoch change in progress, data may be incomplete until it finishes"
+            }
+            ApiError::InsufficientGasBalance(_) => "Insufficient balance to pay for gas",
+            ApiError::SignatureCountMismatch { .. } => {
+                "Number of signatures doesn't match the number of required signers"
+            }
+            ApiError::DuplicateOperation(_) => "Operation index is used more than once",
+            ApiError::IncompatibleApiVersion(_) => {
+                "Rosetta API version is incompatible between the client and server"
+            }
+            ApiError::ConflictingSigners(_) => "Multiple signatures are from the same account",
+            ApiError::MaximumBlockSizeExceeded(_) => "Too many operations for a single transaction",
+            ApiError::FieldValidationFailed(_) => "Multiple fields failed validation",
+            ApiError::TooManyRequests { .. } => "Too many requests, the endpoint is rate limited",
+            ApiError::AccountAlreadyExists(_) => "Account already exists",
+            ApiError::GasPriceUnderMinimum(_) => "Gas price is below the minimum",
+            ApiError::AmountOverflow(_) => "Amount is too large",
+            ApiError::MissingMetadataField(_) => "Required metadata field is missing",
+            ApiError::TestModeOnly => "This endpoint is only available in test mode",
+            ApiError::OperationMetadataTooLarge(_) => "Operation metadata is too large",
+            ApiError::InvalidPublicKeyFormat(_) => "Public key is in an invalid format",
+            ApiError::ConsensusFailure(_) => "Node has lost consensus participation",
+            ApiError::InsufficientStake(_) => "Stake is below the minimum",
+            ApiError::AptosCoinNotRegistered(_) => "Account has not registered APT",
+            ApiError::InvalidHexLength { .. } => "Hex string is the wrong length",
+            ApiError::LedgerVersionTooHigh(_) => "Ledger version is ahead of the latest version",
+            ApiError::PartialMetadata(_) => "Construction metadata is missing required fields",
             ApiError::ResourceNotFound(_) => "Resource not found",
             ApiError::ModuleNotFound(_) => "Module not found",
             ApiError::StructFieldNotFound(_) => "Struct field not found",
@@ -203,8 +424,61 @@ impl ApiError {
             ApiError::InvalidTransferOperations(inner) => inner.map(|inner| inner.to_string()),
             ApiError::UnsupportedCurrency(inner) => inner,
             ApiError::UnsupportedSignatureCount(inner) => inner.map(|inner| inner.to_string()),
+            ApiError::NodeIsOffline(inner) => inner,
             ApiError::TransactionParseError(inner) => inner,
+            ApiError::TransactionExpired(inner) => inner,
+            ApiError::InvalidExpiryTime(inner) => inner,
             ApiError::InternalError(inner) => inner,
+            ApiError::MaxRetriesExceeded { last_error, .. } => {
+                last_error.details().map(|inner| inner.details)
+            }
+            ApiError::UnsupportedOperationType(inner) => inner,
+            ApiError::EpochChangeInProgress(inner) => inner,
+            ApiError::InsufficientGasBalance(inner) => inner,
+            ApiError::SignatureCountMismatch { expected, got } => {
+                Some(format!("Expected {} signatures, got {}", expected, got))
+            }
+            ApiError::DuplicateOperation(inner) => inner,
+            ApiError::IncompatibleApiVersion(inner) => inner,
+            ApiError::ConflictingSigners(inner) => inner,
+            ApiError::MaximumBlockSizeExceeded(inner) => inner,
+            // Serialized as a JSON array, so each field's error can be pulled back out
+            ApiError::FieldValidationFailed(errors) => serde_json::to_string(&errors).ok(),
+            // Serialized as a JSON object, so each limit can be pulled back out
+            ApiError::TooManyRequests {
+                endpoint,
+                retry_after_secs,
+                limit,
+                window_secs,
+            } => serde_json::to_string(&RateLimitDetails {
+                endpoint: endpoint.to_string(),
+                retry_after_secs,
+                limit,
+                window_secs,
+            })
+            .ok(),
+            ApiError::AccountAlreadyExists(inner) => inner,
+            ApiError::GasPriceUnderMinimum(inner) => inner,
+            ApiError::AmountOverflow(inner) => inner,
+            ApiError::MissingMetadataField(inner) => inner,
+            ApiError::OperationMetadataTooLarge(inner) => inner,
+            ApiError::InvalidPublicKeyFormat(inner) => inner,
+            ApiError::ConsensusFailure(inner) => inner,
+            ApiError::InsufficientStake(inner) => inner,
+            ApiError::AptosCoinNotRegistered(inner) => inner,
+            // Serialized as a JSON object, so the lengths can be pulled back out
+            ApiError::InvalidHexLength {
+                expected_bytes,
+                got_bytes,
+                field,
+            } => serde_json::to_string(&HexLengthDetails {
+                field: field.to_string(),
+                expected_bytes,
+                got_bytes,
+            })
+            .ok(),
+            ApiError::LedgerVersionTooHigh(inner) => inner,
+            ApiError::PartialMetadata(inner) => inner,
             ApiError::AccountNotFound(inner) => inner,
             ApiError::ResourceNotFound(inner) => inner,
             ApiError::ModuleNotFound(inner) => inner,
@@ -225,6 +499,155 @@ impl ApiError {
         .map(|details| ErrorDetails { details })
     }
 
+    pub fn recovery_hint(&self) -> RecoveryHint {
+        use ApiError::*;
+        match self {
+            SequenceNumberTooOld(_) => RecoveryHint::new(RecoveryAction::RefreshSequenceNumber),
+            MempoolIsFull(_) => RecoveryHint::retry_after(5),
+            // Epoch changes usually finish within a round, but can take longer
+            EpochChangeInProgress(_) => RecoveryHint::retry_after(15),
+            AccountNotFound(_)
+            | BlockNotFound(_)
+            | TransactionIsPending
+            | ConsensusFailure(_)
+            | LedgerVersionTooHigh(_) => RecoveryHint::new(RecoveryAction::Retry),
+            TooManyRequests {
+                retry_after_secs: Some(retry_after_secs),
+                ..
+            } => RecoveryHint::retry_after(*retry_after_secs),
+            TooManyRequests { .. } => RecoveryHint::new(RecoveryAction::RetryAfter),
+            InsufficientGasBalance(_) => RecoveryHint::new(RecoveryAction::AddFunds),
+            // The details say whether the account itself is missing, so it survives the round trip
+            AptosCoinNotRegistered(Some(details)) if details.ends_with(ACCOUNT_DOES_NOT_EXIST) => {
+                RecoveryHint::new(RecoveryAction::CallCreateAccount)
+            }
+            AptosCoinNotRegistered(_) => RecoveryHint::new(RecoveryAction::CallRegisterCoin),
+            InternalError(_) => RecoveryHint::new(RecoveryAction::ContactSupport),
+            _ => RecoveryHint::new(RecoveryAction::NoAction),
+        }
+    }
+
+    /// Parses an error from any response body, based on its content type
+    ///
+    /// Bodies that aren't a Rosetta error are kept as the details of an [`ApiError::InternalError`]
+    pub fn from_response(status: StatusCode, content_type: &str, body: Bytes) -> ApiError {
+        let error = if content_type.starts_with(JSON) {
+            serde_json::from_slice::<types::Error>(&body).ok()
+        } else if content_type.starts_with(BCS) {
+            bcs::from_bytes::<types::Error>(&body).ok()
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return ApiError::from_code(error.code, error.details.map(|inner| inner.details));
+        }
+
+        match String::from_utf8(body.to_vec()) {
+            Ok(message) if !message.is_empty() => ApiError::InternalError(Some(message)),
+            _ => ApiError::InternalError(Some(format!("Request failed with status {}", status))),
+        }
+    }
+
+    /// Looks up an error by its code, unknown codes become an [`ApiError::InternalError`]
+    pub(crate) fn from_code(code: u32, details: Option<String>) -> ApiError {
+        use ApiError::*;
+        let error = ApiError::all()
+            .into_iter()
+            .find(|error| error.code() == code)
+            .unwrap_or(InternalError(None));
+        match error {
+            DeserializationFailed(_) => DeserializationFailed(details),
+            UnsupportedCurrency(_) => UnsupportedCurrency(details),
+            NodeIsOffline(_) => NodeIsOffline(details),
+            TransactionParseError(_) => TransactionParseError(details),
+            TransactionExpired(_) => TransactionExpired(details),
+            InvalidExpiryTime(_) => InvalidExpiryTime(details),
+            InternalError(_) => InternalError(details),
+            UnsupportedOperationType(_) => UnsupportedOperationType(details),
+            EpochChangeInProgress(_) => EpochChangeInProgress(details),
+            InsufficientGasBalance(_) => InsufficientGasBalance(details),
+            DuplicateOperation(_) => DuplicateOperation(details),
+            IncompatibleApiVersion(_) => IncompatibleApiVersion(details),
+            ConflictingSigners(_) => ConflictingSigners(details),
+            MaximumBlockSizeExceeded(_) => MaximumBlockSizeExceeded(details),
+            FieldValidationFailed(_) => FieldValidationFailed(
+                details
+                    .and_then(|details| serde_json::from_str(&details).ok())
+                    .unwrap_or_default(),
+            ),
+            TooManyRequests { .. } => {
+                let details: Option<RateLimitDetails> =
+                    details.and_then(|details| serde_json::from_str(&details).ok());
+                TooManyRequests {
+                    endpoint: "",
+                    retry_after_secs: details.as_ref().and_then(|inner| inner.retry_after_secs),
+                    limit: details.as_ref().and_then(|inner| inner.limit),
+                    window_secs: details.as_ref().and_then(|inner| inner.window_secs),
+                }
+            }
+            AccountAlreadyExists(_) => AccountAlreadyExists(details),
+            GasPriceUnderMinimum(_) => GasPriceUnderMinimum(details),
+            AmountOverflow(_) => AmountOverflow(details),
+            MissingMetadataField(_) => MissingMetadataField(details),
+            OperationMetadataTooLarge(_) => OperationMetadataTooLarge(details),
+            InvalidPublicKeyFormat(_) => InvalidPublicKeyFormat(details),
+            ConsensusFailure(_) => ConsensusFailure(details),
+            InsufficientStake(_) => InsufficientStake(details),
+            AptosCoinNotRegistered(_) => AptosCoinNotRegistered(details),
+            InvalidHexLength { .. } => {
+                let details: Option<HexLengthDetails> =
+                    details.and_then(|details| serde_json::from_str(&details).ok());
+                InvalidHexLength {
+                    expected_bytes: details.as_ref().map_or(0, |inner| inner.expected_bytes),
+                    got_bytes: details.as_ref().map_or(0, |inner| inner.got_bytes),
+                    field: "",
+                }
+            }
+            LedgerVersionTooHigh(_) => LedgerVersionTooHigh(details),
+            PartialMetadata(_) => PartialMetadata(details),
+            AccountNotFound(_) => AccountNotFound(details),
+            ResourceNotFound(_) => ResourceNotFound(details),
+            ModuleNotFound(_) => ModuleNotFound(details),
+            StructFieldNotFound(_) => StructFieldNotFound(details),
+            VersionNotFound(_) => VersionNotFound(details),
+            TransactionNotFound(_) => TransactionNotFound(details),
+            TableItemNotFound(_) => TableItemNotFound(details),
+            BlockNotFound(_) => BlockNotFound(details),
+            VersionPruned(_) => VersionPruned(details),
+            BlockPruned(_) => BlockPruned(details),
+            InvalidInput(_) => InvalidInput(details),
+            InvalidTransactionUpdate(_) => InvalidTransactionUpdate(details),
+            SequenceNumberTooOld(_) => SequenceNumberTooOld(details),
+            VmError(_) => VmError(details),
+            MempoolIsFull(_) => MempoolIsFull(details),
+            // Errors without free form details can't carry them over
+            error => error,
+        }
+    }
+
+    /// The individual field errors, if this is a [`ApiError::FieldValidationFailed`]
+    pub fn field_errors(self) -> Option<Vec<FieldError>> {
+        match self {
+            ApiError::FieldValidationFailed(errors) => Some(errors),
+            _ => None,
+        }
+    }
+
+    /// The account hasn't registered a `CoinStore` for APT, possibly because it doesn't exist
+    pub fn aptos_coin_not_registered(account: AccountAddress, account_exists: bool) -> ApiError {
+        if account_exists {
+            ApiError::AptosCoinNotRegistered(Some(format!(
+                "Account {} has not registered a CoinStore for APT",
+                account
+            )))
+        } else {
+            ApiError::AptosCoinNotRegistered(Some(format!(
+                "Account {} {}",
+                account, ACCOUNT_DOES_NOT_EXIST
+            )))
+        }
+    }
+
     pub fn deserialization_failed(type_: &str) -> ApiError {
         ApiError::DeserializationFailed(Some(type_.to_string()))
     }
@@ -234,11 +657,29 @@ impl ApiError {
     }
 }
 
+/// Details of an [`ApiError::TooManyRequests`], as separate JSON keys
+#[derive(Deserialize, Serialize)]
+struct RateLimitDetails {
+    endpoint: String,
+    retry_after_secs: Option<u64>,
+    limit: Option<u32>,
+    window_secs: Option<u32>,
+}
+
+/// Details of an [`ApiError::InvalidHexLength`], as separate JSON keys
+#[derive(Deserialize, Serialize)]
+struct HexLengthDetails {
+    field: String,
+    expected_bytes: usize,
+    got_bytes: usize,
+}
+
 impl From<ApiError> for types::Error {
     fn from(error: ApiError) -> Self {
         let message = error.message();
         let code = error.code();
         let retriable = error.retriable();
+        let recovery_hint = Some(error.recovery_hint());
         let details = error.details();
         types::Error {
             message,
@@ -246,6 +687,9 @@ impl From<ApiError> for types::Error {
             retriable,
             details,
             description: None,
+            recovery_hint,
+            // The server has no test fixtures, so it's never in test mode
+            is_test_mode: false,
         }
     }
 }
@@ -253,6 +697,16 @@ impl From<ApiError> for types::Error {
 impl From<RestError> for ApiError {
     fn from(err: RestError) -> Self {
         match err {
+            // Epoch changes can surface through other error codes, so check the message first
+            RestError::Api(err)
+                if err
+                    .error
+                    .message
+                    .to_lowercase()
+                    .contains("epoch change in progress") =>
+            {
+                ApiError::EpochChangeInProgress(Some(err.error.message))
+            }
             RestError::Api(err) => match err.error.error_code {
                 AptosErrorCode::AccountNotFound => {
                     ApiError::AccountNotFound(Some(err.error.message))
@@ -264,6 +718,14 @@ impl From<RestError> for ApiError {
                 AptosErrorCode::StructFieldNotFound => {
                     ApiError::StructFieldNotFound(Some(err.error.message))
                 }
+                // Older nodes report versions that haven't been committed yet as not found
+                AptosErrorCode::VersionNotFound
+                    if VERSION_AHEAD_OF_LEDGER
+                        .iter()
+                        .any(|pattern| err.error.message.contains(pattern)) =>
+                {
+                    ApiError::LedgerVersionTooHigh(Some(err.error.message))
+                }
                 AptosErrorCode::VersionNotFound => {
                     ApiError::VersionNotFound(Some(err.error.message))
                 }
@@ -274,6 +736,9 @@ impl From<RestError> for ApiError {
                     ApiError::TableItemNotFound(Some(err.error.message))
                 }
                 AptosErrorCode::BlockNotFound => ApiError::BlockNotFound(Some(err.error.message)),
+                AptosErrorCode::LedgerVersionTooHigh => {
+                    ApiError::LedgerVersionTooHigh(Some(err.error.message))
+                }
                 AptosErrorCode::VersionPruned => ApiError::VersionPruned(Some(err.error.message)),
                 AptosErrorCode::BlockPruned => ApiError::BlockPruned(Some(err.error.message)),
                 AptosErrorCode::InvalidInput => ApiError::InvalidInput(Some(err.error.message)),
@@ -283,11 +748,28 @@ impl From<RestError> for ApiError {
                 AptosErrorCode::SequenceNumberTooOld => {
                     ApiError::SequenceNumberTooOld(Some(err.error.message))
                 }
-                AptosErrorCode::VmError => ApiError::VmError(Some(err.error.message)),
+                AptosErrorCode::VmError => match err.error.vm_error_code {
+                    Some(code)
+                        if code
+                            == VmStatusCode::INSUFFICIENT_BALANCE_FOR_TRANSACTION_FEE as u64 =>
+                    {
+                        ApiError::InsufficientGasBalance(Some(err.error.message))
+                    }
+                    Some(code) if code == VmStatusCode::TRANSACTION_EXPIRED as u64 => {
+                        ApiError::TransactionExpired(Some(err.error.message))
+                    }
+                    Some(code) if code == VmStatusCode::GAS_UNIT_PRICE_BELOW_MIN_BOUND as u64 => {
+                        ApiError::GasPriceUnderMinimum(Some(err.error.message))
+                    }
+                    _ => ApiError::VmError(Some(err.error.message)),
+                },
                 AptosErrorCode::HealthCheckFailed => {
                     ApiError::InternalError(Some(err.error.message))
                 }
                 AptosErrorCode::MempoolIsFull => ApiError::MempoolIsFull(Some(err.error.message)),
+                AptosErrorCode::EpochChangeInProgress => {
+                    ApiError::EpochChangeInProgress(Some(err.error.message))
+                }
                 AptosErrorCode::WebFrameworkError => {
                     ApiError::InternalError(Some(err.error.message))
                 }
@@ -335,6 +817,24 @@ impl From<std::num::ParseIntError> for ApiError {
     }
 }
 
+impl From<std::io::Error> for ApiError {
+    fn from(err: std::io::Error) -> Self {
+        ApiError::InternalError(Some(err.to_string()))
+    }
+}
+
+impl From<ApiError> for std::io::Error {
+    fn from(err: ApiError) -> Self {
+        let kind = match err {
+            ApiError::NodeIsOffline(_) => std::io::ErrorKind::ConnectionRefused,
+            ApiError::InternalError(_) => std::io::ErrorKind::Other,
+            ApiError::AccountNotFound(_) => std::io::ErrorKind::NotFound,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, err)
+    }
+}
+
 impl warp::reject::Reject for ApiError {}
 
 impl Reply for ApiError {
@@ -342,3 +842,77 @@ impl Reply for ApiError {
         warp::reply::json(&self.into_error()).into_response()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aptos_rest_client::aptos_api_types::AptosError;
+    use aptos_rest_client::error::AptosErrorResponse;
+
+    fn vm_error(vm_status: VmStatusCode) -> RestError {
+        RestError::Api(AptosErrorResponse {
+            error: AptosError::new_with_vm_status(
+                "Invalid transaction",
+                AptosErrorCode::VmError,
+                vm_status,
+            ),
+            state: None,
+            status_code: reqwest::StatusCode::BAD_REQUEST,
+        })
+    }
+
+    fn api_error(message: &str, error_code: AptosErrorCode) -> RestError {
+        RestError::Api(AptosErrorResponse {
+            error: AptosError::new_with_error_code(message, error_code),
+            state: None,
+            status_code: reqwest::StatusCode::NOT_FOUND,
+        })
+    }
+
+    #[test]
+    fn test_from_rest_error_version_ahead_of_ledger() {
+        assert!(matches!(
+            ApiError::from(api_error(
+                "Ledger version(100) is ahead of the latest ledger version",
+                AptosErrorCode::LedgerVersionTooHigh
+            )),
+            ApiError::LedgerVersionTooHigh(_)
+        ));
+
+        // Older nodes report it as not found
+        assert!(matches!(
+            ApiError::from(api_error(
+                "Ledger version not found by Ledger version(100)",
+                AptosErrorCode::VersionNotFound
+            )),
+            ApiError::LedgerVersionTooHigh(_)
+        ));
+        assert!(matches!(
+            ApiError::from(api_error(
+                "Transaction not found by Ledger version(100)",
+                AptosErrorCode::VersionNotFound
+            )),
+            ApiError::VersionNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_from_rest_error() {
+        assert!(matches!(
+            ApiError::from(vm_error(
+                VmStatusCode::INSUFFICIENT_BALANCE_FOR_TRANSACTION_FEE
+            )),
+            ApiError::InsufficientGasBalance(_)
+        ));
+        assert!(matches!(
+            ApiError::from(vm_error(VmStatusCode::GAS_UNIT_PRICE_BELOW_MIN_BOUND)),
+            ApiError::GasPriceUnderMinimum(_)
+        ));
+
+        // Prologue aborts reach the API as status codes, anything else is a generic VM error
+        assert!(matches!(
+            ApiError::from(vm_error(VmStatusCode::SEQUENCE_NUMBER_TOO_NEW)),
+            ApiError::VmError(_)
+        ));
+    }
+}
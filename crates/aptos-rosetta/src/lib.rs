@@ -61,7 +61,7 @@ impl RosettaContext {
         if let Some(ref client) = self.rest_client {
             Ok(client.clone())
         } else {
-            Err(ApiError::NodeIsOffline)
+            Err(ApiError::NodeIsOffline(None))
         }
     }
 
@@ -69,7 +69,7 @@ impl RosettaContext {
         if let Some(ref block_cache) = self.block_cache {
             Ok(block_cache.clone())
         } else {
-            Err(ApiError::NodeIsOffline)
+            Err(ApiError::NodeIsOffline(None))
         }
     }
 }
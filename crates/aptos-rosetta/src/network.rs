@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    common::{check_network, handle_request, with_context, with_empty_request},
+    common::{check_network, handle_request, native_coin, with_context, with_empty_request},
     error::ApiError,
     types::{
         Allow, MetadataRequest, NetworkListResponse, NetworkOptionsResponse, NetworkRequest,
@@ -95,10 +95,6 @@ async fn network_options(
         .into_iter()
         .map(|status| status.into())
         .collect();
-    let operation_types = OperationType::all()
-        .into_iter()
-        .map(|op| op.to_string())
-        .collect();
     let errors = ApiError::all()
         .into_iter()
         .map(|err| err.into_error())
@@ -106,7 +102,7 @@ async fn network_options(
 
     let allow = Allow {
         operation_statuses,
-        operation_types,
+        operation_types: allowed_operation_types(),
         errors,
         historical_balance_lookup: true,
         timestamp_start_index: None,
@@ -115,6 +111,8 @@ async fn network_options(
         mempool_coins: false,
         block_hash_case: None,
         transaction_hash_case: None,
+        // Only the native coin can be transferred
+        currencies: Some(vec![native_coin()]),
     };
 
     let response = NetworkOptionsResponse { version, allow };
@@ -122,6 +120,14 @@ async fn network_options(
     Ok(response)
 }
 
+/// The operation types advertised in the network options
+pub(crate) fn allowed_operation_types() -> Vec<String> {
+    OperationType::all()
+        .into_iter()
+        .map(|op| op.to_string())
+        .collect()
+}
+
 /// Get network status including the latest state
 ///
 /// This should respond with the latest ledger version, timestamp, and genesis information
@@ -82,7 +82,7 @@ impl BlockIdentifier {
 /// Identifier for this specific network deployment
 ///
 /// [API Spec](https://www.rosetta-api.org/docs/models/NetworkIdentifier.html)
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct NetworkIdentifier {
     /// Blockchain name, should always be `aptos` and be hardcoded
     pub blockchain: String,
@@ -180,7 +180,7 @@ pub struct SubAccountIdentifier {
 /// Sub network identifier if there are sub networks
 ///
 /// [API Spec](https://www.rosetta-api.org/docs/models/SubNetworkIdentifier.html)
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct SubNetworkIdentifier {
     pub network: String,
 }
@@ -26,8 +26,22 @@ pub struct Error {
     /// Specific details of the error e.g. stack trace
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details: Option<ErrorDetails>,
+    /// Machine readable guidance on how to recover from the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery_hint: Option<RecoveryHint>,
+    /// Whether the server is running with test fixtures, which enables test only endpoints
+    #[serde(default)]
+    pub is_test_mode: bool,
 }
 
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed API with: {:?}", self)
+    }
+}
+
+impl std::error::Error for Error {}
+
 /// Error details that are specific to the instance
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ErrorDetails {
@@ -35,6 +49,59 @@ pub struct ErrorDetails {
     pub details: String,
 }
 
+/// Guidance on how a caller can recover from an error
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RecoveryHint {
+    /// What the caller should do next
+    pub action: RecoveryAction,
+    /// How long to wait before trying again, if applicable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay_secs: Option<u64>,
+    /// Link to more information about the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documentation_url: Option<String>,
+}
+
+impl RecoveryHint {
+    pub fn new(action: RecoveryAction) -> RecoveryHint {
+        RecoveryHint {
+            action,
+            delay_secs: None,
+            documentation_url: None,
+        }
+    }
+
+    pub fn retry_after(delay_secs: u64) -> RecoveryHint {
+        RecoveryHint {
+            action: RecoveryAction::RetryAfter,
+            delay_secs: Some(delay_secs),
+            documentation_url: None,
+        }
+    }
+}
+
+/// Action to take to recover from an error
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryAction {
+    /// Retry the same request
+    Retry,
+    /// Retry the same request after a delay
+    RetryAfter,
+    /// Rebuild the transaction with an up to date sequence number
+    RefreshSequenceNumber,
+    /// Fund the account before trying again
+    AddFunds,
+    /// Create the account before trying again
+    CallCreateAccount,
+    /// Register the coin's `CoinStore` in the account before trying again
+    CallRegisterCoin,
+    /// The error is unexpected, and should be reported
+    ContactSupport,
+    /// The request can't succeed as is, and must be changed
+    NoAction,
+}
+
 /// Status of an operation
 ///
 /// [API Spec](https://www.rosetta-api.org/docs/models/OperationStatus.html)
@@ -64,6 +131,22 @@ pub struct SyncStatus {
     synced: bool,
 }
 
+impl SyncStatus {
+    /// Number of blocks the node is behind its target, if it isn't synced
+    pub fn blocks_behind(&self) -> Option<u64> {
+        if self.synced {
+            return None;
+        }
+
+        match (self.current_index, self.target_index) {
+            (Some(current_index), Some(target_index)) => {
+                Some(target_index.saturating_sub(current_index))
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Version information for the current deployment to handle software version matching
 ///
 /// [API Spec](https://www.rosetta-api.org/docs/models/Version.html)
@@ -86,6 +169,10 @@ pub enum OperationType {
     Withdraw,
     Deposit,
     SetOperator,
+    AddValidatorStake,
+    UnlockStake,
+    WithdrawInactiveStake,
+    RotateKey,
     // Fee must always be last for ordering
     Fee,
 }
@@ -96,6 +183,10 @@ impl OperationType {
     const WITHDRAW: &'static str = "withdraw";
     const FEE: &'static str = "fee";
     const SET_OPERATOR: &'static str = "set_operator";
+    const ADD_VALIDATOR_STAKE: &'static str = "add_validator_stake";
+    const UNLOCK_STAKE: &'static str = "unlock_stake";
+    const WITHDRAW_INACTIVE_STAKE: &'static str = "withdraw_inactive_stake";
+    const ROTATE_KEY: &'static str = "rotate_key";
 
     pub fn all() -> Vec<OperationType> {
         vec![
@@ -104,6 +195,10 @@ impl OperationType {
             OperationType::Deposit,
             OperationType::Fee,
             OperationType::SetOperator,
+            OperationType::AddValidatorStake,
+            OperationType::UnlockStake,
+            OperationType::WithdrawInactiveStake,
+            OperationType::RotateKey,
         ]
     }
 }
@@ -118,6 +213,10 @@ impl FromStr for OperationType {
             Self::WITHDRAW => Ok(OperationType::Withdraw),
             Self::FEE => Ok(OperationType::Fee),
             Self::SET_OPERATOR => Ok(OperationType::SetOperator),
+            Self::ADD_VALIDATOR_STAKE => Ok(OperationType::AddValidatorStake),
+            Self::UNLOCK_STAKE => Ok(OperationType::UnlockStake),
+            Self::WITHDRAW_INACTIVE_STAKE => Ok(OperationType::WithdrawInactiveStake),
+            Self::ROTATE_KEY => Ok(OperationType::RotateKey),
             _ => Err(ApiError::DeserializationFailed(Some(format!(
                 "Invalid OperationType: {}",
                 s
@@ -133,6 +232,10 @@ impl Display for OperationType {
             OperationType::Deposit => Self::DEPOSIT,
             OperationType::Withdraw => Self::WITHDRAW,
             OperationType::SetOperator => Self::SET_OPERATOR,
+            OperationType::AddValidatorStake => Self::ADD_VALIDATOR_STAKE,
+            OperationType::UnlockStake => Self::UNLOCK_STAKE,
+            OperationType::WithdrawInactiveStake => Self::WITHDRAW_INACTIVE_STAKE,
+            OperationType::RotateKey => Self::ROTATE_KEY,
             OperationType::Fee => Self::FEE,
         })
     }
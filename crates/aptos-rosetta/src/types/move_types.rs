@@ -57,6 +57,22 @@ pub fn set_operator_function_identifier() -> Identifier {
     ident_str!("set_operator").into()
 }
 
+pub fn add_stake_function_identifier() -> Identifier {
+    ident_str!("add_stake").into()
+}
+
+pub fn unlock_function_identifier() -> Identifier {
+    ident_str!("unlock").into()
+}
+
+pub fn withdraw_function_identifier() -> Identifier {
+    ident_str!("withdraw").into()
+}
+
+pub fn rotate_authentication_key_ed25519_function_identifier() -> Identifier {
+    ident_str!("rotate_authentication_key_ed25519").into()
+}
+
 // Field identifiers
 pub fn decimals_field_identifier() -> Identifier {
     ident_str!("decimals").into()
@@ -74,6 +90,10 @@ pub fn set_operator_events_field_identifier() -> Identifier {
     ident_str!("set_operator_events").into()
 }
 
+pub fn add_stake_events_field_identifier() -> Identifier {
+    ident_str!("add_stake_events").into()
+}
+
 pub fn sequence_number_field_identifier() -> Identifier {
     ident_str!("sequence_number").into()
 }
@@ -5,12 +5,16 @@
 //!
 //! [Spec](https://www.rosetta-api.org/docs/api_objects.html)
 
-use crate::common::native_coin_tag;
+use crate::common::{
+    decode_public_key, encode_public_key, get_required_metadata_field, native_coin_tag,
+    validate_hex_bytes,
+};
 use crate::types::{
-    account_module_identifier, aptos_coin_module_identifier, aptos_coin_resource_identifier,
-    coin_module_identifier, create_account_function_identifier,
-    set_operator_events_field_identifier, set_operator_function_identifier,
-    stake_module_identifier, stake_pool_resource_identifier, transfer_function_identifier,
+    account_module_identifier, add_stake_events_field_identifier, add_stake_function_identifier,
+    aptos_coin_module_identifier, aptos_coin_resource_identifier, coin_module_identifier,
+    create_account_function_identifier, set_operator_events_field_identifier,
+    set_operator_function_identifier, stake_module_identifier, stake_pool_resource_identifier,
+    transfer_function_identifier,
 };
 use crate::{
     common::{is_native_coin, native_coin},
@@ -24,8 +28,9 @@ use crate::{
     },
     ApiError,
 };
-use anyhow::anyhow;
-use aptos_crypto::{ed25519::Ed25519PublicKey, ValidCryptoMaterialStringExt};
+use aptos_crypto::ed25519::{
+    Ed25519PublicKey, ED25519_PUBLIC_KEY_LENGTH, ED25519_SIGNATURE_LENGTH,
+};
 use aptos_rest_client::aptos_api_types::{
     Address, Event, MoveStructTag, MoveType, TransactionPayload, UserTransactionRequest,
     WriteResource,
@@ -78,6 +83,10 @@ pub struct Allow {
     /// Case specifics for transaction hashes.  Set to None if case insensitive
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transaction_hash_case: Option<Case>,
+    /// Currencies that can be used in operations, this isn't part of the Rosetta spec so older
+    /// servers won't have it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currencies: Option<Vec<Currency>>,
 }
 
 /// Amount of a [`Currency`] in atomic units
@@ -165,9 +174,9 @@ pub enum BlockEventType {
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct BlockTransaction {
     /// Block associated with transaction
-    block_identifier: BlockIdentifier,
+    pub block_identifier: BlockIdentifier,
     /// Transaction associated with block
-    transaction: Transaction,
+    pub transaction: Transaction,
 }
 
 /// Tells what cases are supported in hashes. Having no value is case insensitive.
@@ -383,6 +392,71 @@ impl Operation {
             Some(OperationMetadata::set_operator(operator)),
         )
     }
+
+    pub fn add_validator_stake(
+        operation_index: u64,
+        status: Option<OperationStatusType>,
+        owner: AccountAddress,
+        amount: u64,
+    ) -> Operation {
+        Operation::new(
+            OperationType::AddValidatorStake,
+            operation_index,
+            status,
+            owner,
+            None,
+            Some(OperationMetadata::add_validator_stake(amount)),
+        )
+    }
+
+    pub fn unlock_stake(
+        operation_index: u64,
+        status: Option<OperationStatusType>,
+        owner: AccountAddress,
+        amount: u64,
+    ) -> Operation {
+        Operation::new(
+            OperationType::UnlockStake,
+            operation_index,
+            status,
+            owner,
+            None,
+            Some(OperationMetadata::unlock_stake(amount)),
+        )
+    }
+
+    /// Withdraws inactive stake, where no amount withdraws all of it
+    pub fn withdraw_inactive_stake(
+        operation_index: u64,
+        status: Option<OperationStatusType>,
+        owner: AccountAddress,
+        amount: Option<u64>,
+    ) -> Operation {
+        Operation::new(
+            OperationType::WithdrawInactiveStake,
+            operation_index,
+            status,
+            owner,
+            None,
+            Some(OperationMetadata::withdraw_inactive_stake(amount)),
+        )
+    }
+
+    pub fn rotate_key(
+        operation_index: u64,
+        status: Option<OperationStatusType>,
+        account: AccountAddress,
+        rotate_key: &RotateKey,
+    ) -> Operation {
+        Operation::new(
+            OperationType::RotateKey,
+            operation_index,
+            status,
+            account,
+            None,
+            Some(OperationMetadata::rotate_key(rotate_key)),
+        )
+    }
 }
 
 impl std::cmp::PartialOrd for Operation {
@@ -415,9 +489,28 @@ pub struct OperationMetadata {
     sender: Option<AccountIdentifier>,
     #[serde(skip_serializing_if = "Option::is_none")]
     operator: Option<AccountIdentifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount: Option<U64>,
+    /// Hex encoded ed25519 public key currently used by the account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_public_key: Option<String>,
+    /// Hex encoded ed25519 public key the account is rotating to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_public_key: Option<String>,
+    /// Hex encoded signature of the rotation proof challenge by the current key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_signature: Option<String>,
+    /// Hex encoded signature of the rotation proof challenge by the new key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_signature: Option<String>,
 }
 
 impl OperationMetadata {
+    /// Account sending the transaction, for operations that act on another account
+    pub fn sender(&self) -> Option<&AccountIdentifier> {
+        self.sender.as_ref()
+    }
+
     pub fn create_account(sender: AccountAddress) -> Self {
         OperationMetadata {
             sender: Some(sender.into()),
@@ -431,6 +524,37 @@ impl OperationMetadata {
             ..Default::default()
         }
     }
+
+    pub fn add_validator_stake(amount: u64) -> Self {
+        OperationMetadata {
+            amount: Some(U64(amount)),
+            ..Default::default()
+        }
+    }
+
+    pub fn unlock_stake(amount: u64) -> Self {
+        OperationMetadata {
+            amount: Some(U64(amount)),
+            ..Default::default()
+        }
+    }
+
+    pub fn withdraw_inactive_stake(amount: Option<u64>) -> Self {
+        OperationMetadata {
+            amount: amount.map(U64),
+            ..Default::default()
+        }
+    }
+
+    pub fn rotate_key(rotate_key: &RotateKey) -> Self {
+        OperationMetadata {
+            current_public_key: Some(hex::encode(&rotate_key.current_public_key)),
+            new_public_key: Some(hex::encode(&rotate_key.new_public_key)),
+            current_signature: Some(hex::encode(&rotate_key.current_signature)),
+            new_signature: Some(hex::encode(&rotate_key.new_signature)),
+            ..Default::default()
+        }
+    }
 }
 
 /// Used for query operations to apply conditions.  Defaults to [`Operator::And`] if no value is
@@ -466,10 +590,7 @@ impl TryFrom<Ed25519PublicKey> for PublicKey {
     type Error = anyhow::Error;
 
     fn try_from(public_key: Ed25519PublicKey) -> Result<Self, Self::Error> {
-        Ok(PublicKey {
-            hex_bytes: public_key.to_encoded_string()?,
-            curve_type: CurveType::Edwards25519,
-        })
+        encode_public_key(&public_key)
     }
 }
 
@@ -477,13 +598,7 @@ impl TryFrom<PublicKey> for Ed25519PublicKey {
     type Error = anyhow::Error;
 
     fn try_from(public_key: PublicKey) -> Result<Self, Self::Error> {
-        if public_key.curve_type != CurveType::Edwards25519 {
-            return Err(anyhow!("Invalid curve type"));
-        }
-
-        Ok(Ed25519PublicKey::from_encoded_string(
-            &public_key.hex_bytes,
-        )?)
+        decode_public_key(&public_key)
     }
 }
 
@@ -746,6 +861,19 @@ fn parse_operations_from_txn_payload(
                 operator.into(),
                 sender,
             ));
+        } else if AccountAddress::ONE == *inner.function.module.address.inner()
+            && stake_module_identifier() == inner.function.module.name.0
+            && add_stake_function_identifier() == inner.function.name.0
+        {
+            let amount = serde_json::from_value::<U64>(inner.arguments.get(0).cloned().unwrap())
+                .unwrap()
+                .0;
+            operations.push(Operation::add_validator_stake(
+                operation_index,
+                Some(OperationStatusType::Failure),
+                sender,
+                amount,
+            ));
         }
     }
     operations
@@ -825,6 +953,20 @@ fn parse_operations_from_write_set(
                             operation_index += 1;
                         }
                     }
+                } else if id.0 == add_stake_events_field_identifier() {
+                    if let Ok(event) = serde_json::from_value::<EventId>(value.clone()) {
+                        let add_stake_event =
+                            EventKey::new(event.guid.id.creation_num.0, event.guid.id.addr);
+                        if let Some(amount) = get_add_stake_from_event(events, add_stake_event) {
+                            operations.push(Operation::add_validator_stake(
+                                operation_index,
+                                Some(OperationStatusType::Success),
+                                address,
+                                amount,
+                            ));
+                            operation_index += 1;
+                        }
+                    }
                 }
             }
         } else if data.typ == coin_store_tag {
@@ -899,6 +1041,21 @@ fn get_set_operator_from_event(events: &[Event], event_key: EventKey) -> Option<
     None
 }
 
+fn get_add_stake_from_event(events: &[Event], event_key: EventKey) -> Option<u64> {
+    if let Some(event) = events
+        .iter()
+        .find(|event| EventKey::from(event.key) == event_key)
+    {
+        if let Ok(AddStakeEvent { amount_added, .. }) =
+            serde_json::from_value::<AddStakeEvent>(event.data.clone())
+        {
+            return Some(amount_added.0);
+        }
+    }
+
+    None
+}
+
 /// An enum for processing which operation is in a transaction
 pub enum OperationDetails {
     CreateAccount,
@@ -916,6 +1073,10 @@ pub enum InternalOperation {
     CreateAccount(CreateAccount),
     Transfer(Transfer),
     SetOperator(SetOperator),
+    AddValidatorStake(AddValidatorStake),
+    UnlockStake(UnlockStake),
+    WithdrawInactiveStake(WithdrawInactiveStake),
+    RotateKey(RotateKey),
 }
 
 impl InternalOperation {
@@ -926,6 +1087,7 @@ impl InternalOperation {
                 if let Some(operation) = operations.first() {
                     match OperationType::from_str(&operation.operation_type) {
                         Ok(OperationType::CreateAccount) => {
+                            require_metadata_fields(operation, &["sender"])?;
                             if let (
                                 Some(OperationMetadata {
                                     sender: Some(sender),
@@ -941,6 +1103,7 @@ impl InternalOperation {
                             }
                         }
                         Ok(OperationType::SetOperator) => {
+                            require_metadata_fields(operation, &["operator"])?;
                             if let (
                                 Some(OperationMetadata {
                                     operator: Some(operator),
@@ -955,6 +1118,96 @@ impl InternalOperation {
                                 }));
                             }
                         }
+                        Ok(OperationType::AddValidatorStake) => {
+                            require_metadata_fields(operation, &["amount"])?;
+                            if let (
+                                Some(OperationMetadata {
+                                    amount: Some(amount),
+                                    ..
+                                }),
+                                Some(account),
+                            ) = (&operation.metadata, &operation.account)
+                            {
+                                return Ok(Self::AddValidatorStake(AddValidatorStake {
+                                    owner: account.account_address()?,
+                                    amount: amount.0,
+                                }));
+                            }
+                        }
+                        Ok(OperationType::UnlockStake) => {
+                            require_metadata_fields(operation, &["amount"])?;
+                            if let (
+                                Some(OperationMetadata {
+                                    amount: Some(amount),
+                                    ..
+                                }),
+                                Some(account),
+                            ) = (&operation.metadata, &operation.account)
+                            {
+                                return Ok(Self::UnlockStake(UnlockStake {
+                                    owner: account.account_address()?,
+                                    amount: amount.0,
+                                }));
+                            }
+                        }
+                        Ok(OperationType::WithdrawInactiveStake) => {
+                            if let Some(account) = &operation.account {
+                                return Ok(Self::WithdrawInactiveStake(WithdrawInactiveStake {
+                                    owner: account.account_address()?,
+                                    amount: operation
+                                        .metadata
+                                        .as_ref()
+                                        .and_then(|metadata| metadata.amount)
+                                        .map(|amount| amount.0),
+                                }));
+                            }
+                        }
+                        Ok(OperationType::RotateKey) => {
+                            require_metadata_fields(
+                                operation,
+                                &[
+                                    "current_public_key",
+                                    "new_public_key",
+                                    "current_signature",
+                                    "new_signature",
+                                ],
+                            )?;
+                            if let (
+                                Some(OperationMetadata {
+                                    current_public_key: Some(current_public_key),
+                                    new_public_key: Some(new_public_key),
+                                    current_signature: Some(current_signature),
+                                    new_signature: Some(new_signature),
+                                    ..
+                                }),
+                                Some(account),
+                            ) = (&operation.metadata, &operation.account)
+                            {
+                                return Ok(Self::RotateKey(RotateKey {
+                                    account: account.account_address()?,
+                                    current_public_key: validate_hex_bytes(
+                                        current_public_key,
+                                        ED25519_PUBLIC_KEY_LENGTH,
+                                        "current_public_key",
+                                    )?,
+                                    new_public_key: validate_hex_bytes(
+                                        new_public_key,
+                                        ED25519_PUBLIC_KEY_LENGTH,
+                                        "new_public_key",
+                                    )?,
+                                    current_signature: validate_hex_bytes(
+                                        current_signature,
+                                        ED25519_SIGNATURE_LENGTH,
+                                        "current_signature",
+                                    )?,
+                                    new_signature: validate_hex_bytes(
+                                        new_signature,
+                                        ED25519_SIGNATURE_LENGTH,
+                                        "new_signature",
+                                    )?,
+                                }));
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -973,6 +1226,10 @@ impl InternalOperation {
             Self::CreateAccount(inner) => inner.sender,
             Self::Transfer(inner) => inner.sender,
             Self::SetOperator(inner) => inner.owner,
+            Self::AddValidatorStake(inner) => inner.owner,
+            Self::UnlockStake(inner) => inner.owner,
+            Self::WithdrawInactiveStake(inner) => inner.owner,
+            Self::RotateKey(inner) => inner.account,
         }
     }
 }
@@ -1091,6 +1348,54 @@ pub struct SetOperator {
     pub operator: AccountAddress,
 }
 
+/// Add stake to a validator's own stake pool
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AddValidatorStake {
+    pub owner: AccountAddress,
+    pub amount: u64,
+}
+
+/// Unlock active stake, so it can be withdrawn once the lockup expires
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UnlockStake {
+    pub owner: AccountAddress,
+    pub amount: u64,
+}
+
+/// Withdraw unlocked stake back to the owner, `None` withdraws all of it
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WithdrawInactiveStake {
+    pub owner: AccountAddress,
+    pub amount: Option<u64>,
+}
+
+/// Rotate an account's authentication key to a new ed25519 key
+///
+/// Both keys sign the account's `0x1::account::RotationProofChallenge`, proving the rotation is
+/// intended by the owner of the current key, and that the new key is held by someone
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RotateKey {
+    pub account: AccountAddress,
+    pub current_public_key: Vec<u8>,
+    pub new_public_key: Vec<u8>,
+    pub current_signature: Vec<u8>,
+    pub new_signature: Vec<u8>,
+}
+
+/// Checks that an [`Operation`]'s metadata has every key required by its operation type
+fn require_metadata_fields(operation: &Operation, keys: &[&'static str]) -> ApiResult<()> {
+    let map: HashMap<String, serde_json::Value> = match operation.metadata {
+        Some(ref metadata) => serde_json::to_value(metadata)
+            .and_then(serde_json::from_value)
+            .map_err(|err| ApiError::DeserializationFailed(Some(err.to_string())))?,
+        None => HashMap::new(),
+    };
+    for key in keys {
+        get_required_metadata_field(&map, key)?;
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct CoinEvent {
     amount: U64,
@@ -1103,6 +1408,11 @@ pub struct SetOperatorEvent {
     new_operator: Address,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct AddStakeEvent {
+    amount_added: U64,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct EventId {
     guid: Id,
@@ -1141,3 +1451,53 @@ where
         Ok(AccountAddress::new(value.0))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_add_stake_event() {
+        let pool_address = AccountAddress::from_hex_literal("0x1234").unwrap();
+        let change: WriteSetChange = serde_json::from_value(json!({
+            "type": "write_resource",
+            "address": pool_address.to_hex_literal(),
+            "state_key_hash": "",
+            "data": {
+                "type": "0x1::stake::StakePool",
+                "data": {
+                    "add_stake_events": {
+                        "counter": "1",
+                        "guid": {
+                            "id": {
+                                "addr": pool_address.to_hex_literal(),
+                                "creation_num": "4"
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let events = vec![Event {
+            key: EventKey::new(4, pool_address).into(),
+            sequence_number: U64(0),
+            typ: serde_json::from_value(json!("0x1::stake::AddStakeEvent")).unwrap(),
+            data: json!({
+                "pool_address": pool_address.to_hex_literal(),
+                "amount_added": "1000"
+            }),
+        }];
+
+        assert_eq!(
+            vec![Operation::add_validator_stake(
+                0,
+                Some(OperationStatusType::Success),
+                pool_address,
+                1000
+            )],
+            parse_operations_from_write_set(&change, &events, &None, 0)
+        );
+    }
+}
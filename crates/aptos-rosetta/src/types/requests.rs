@@ -2,9 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::types::{
-    AccountIdentifier, Allow, Amount, Block, BlockIdentifier, Currency, InternalOperation,
-    NetworkIdentifier, Operation, PartialBlockIdentifier, Peer, PublicKey, Signature,
-    SigningPayload, SyncStatus, Transaction, TransactionIdentifier, Version,
+    AccountIdentifier, Allow, Amount, Block, BlockIdentifier, BlockTransaction, Currency,
+    InternalOperation, NetworkIdentifier, Operation, Operator, PartialBlockIdentifier, Peer,
+    PublicKey, Signature, SigningPayload, SyncStatus, Transaction, TransactionIdentifier, Version,
 };
 use aptos_types::chain_id::ChainId;
 use serde::{Deserialize, Serialize};
@@ -106,6 +106,12 @@ pub struct ConstructionCombineRequest {
     pub unsigned_transaction: String,
     /// Set of signatures with SigningPayloads to combine
     pub signatures: Vec<Signature>,
+    /// Hex encoded [`aptos_crypto::multi_ed25519::MultiEd25519PublicKey`] of a MultiEd25519
+    /// signer, whose signatures are one for each of its signing keys
+    ///
+    /// This is an Aptos extension, the Rosetta spec has no way to describe multi-key signers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multi_ed25519_public_key: Option<String>,
 }
 
 /// Response of signed transaction for submission
@@ -217,6 +223,9 @@ pub struct ConstructionMetadata {
     /// Expiry time of the transaction in unix epoch seconds
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expiry_time_secs: Option<u64>,
+    /// Lowest gas price the chain accepts, if the server could look it up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_gas_price_per_unit: Option<u64>,
 }
 
 /// Request to parse a signed or unsigned transaction into operations
@@ -437,6 +446,88 @@ pub struct NetworkStatusResponse {
     pub peers: Vec<Peer>,
 }
 
+/// Request to search for transactions matching a set of conditions
+///
+/// [API Spec](https://www.rosetta-api.org/docs/models/SearchTransactionsRequest.html)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SearchTransactionsRequest {
+    /// Network identifier describing the blockchain and the chain id
+    pub network_identifier: NetworkIdentifier,
+    /// Whether all conditions must match, or any of them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator: Option<Operator>,
+    /// The newest block to search from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_block: Option<u64>,
+    /// Offset into the results, for pagination
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// Maximum number of transactions to return
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    /// Search for a specific transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_identifier: Option<TransactionIdentifier>,
+    /// Search for transactions with operations on an account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_identifier: Option<AccountIdentifier>,
+    /// Search for transactions with operations in a currency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Currency>,
+    /// Search for transactions with operations of a status e.g. success
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Search for transactions with operations of a type e.g. transfer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "type")]
+    pub operation_type: Option<String>,
+    /// Search for transactions with operations on an address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Search for transactions that succeeded or failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success: Option<bool>,
+}
+
+/// Response with the transactions matching a search
+///
+/// [API Spec](https://www.rosetta-api.org/docs/models/SearchTransactionsResponse.html)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SearchTransactionsResponse {
+    /// Transactions matching the search, and the blocks they're in
+    pub transactions: Vec<BlockTransaction>,
+    /// Number of transactions matching the search, across all pages
+    pub total_count: u64,
+    /// Offset of the next page, if there are more results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<u64>,
+}
+
+/// Response with the transactions waiting in mempool
+///
+/// [API Spec](https://www.rosetta-api.org/docs/models/MempoolResponse.html)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MempoolResponse {
+    pub transaction_identifiers: Vec<TransactionIdentifier>,
+}
+
+/// Request for a transaction waiting in mempool
+///
+/// [API Spec](https://www.rosetta-api.org/docs/models/MempoolTransactionRequest.html)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MempoolTransactionRequest {
+    pub network_identifier: NetworkIdentifier,
+    pub transaction_identifier: TransactionIdentifier,
+}
+
+/// Response with a transaction waiting in mempool
+///
+/// [API Spec](https://www.rosetta-api.org/docs/models/MempoolTransactionResponse.html)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MempoolTransactionResponse {
+    pub transaction: Transaction,
+}
+
 /// Response with a transaction that was hashed or submitted
 ///
 /// [API Spec](https://www.rosetta-api.org/docs/models/TransactionIdentifierResponse.html)
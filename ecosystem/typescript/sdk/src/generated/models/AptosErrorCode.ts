@@ -15,6 +15,7 @@ export enum AptosErrorCode {
     TRANSACTION_NOT_FOUND = 'transaction_not_found',
     TABLE_ITEM_NOT_FOUND = 'table_item_not_found',
     BLOCK_NOT_FOUND = 'block_not_found',
+    LEDGER_VERSION_TOO_HIGH = 'ledger_version_too_high',
     VERSION_PRUNED = 'version_pruned',
     BLOCK_PRUNED = 'block_pruned',
     INVALID_INPUT = 'invalid_input',
@@ -23,6 +24,7 @@ export enum AptosErrorCode {
     VM_ERROR = 'vm_error',
     HEALTH_CHECK_FAILED = 'health_check_failed',
     MEMPOOL_IS_FULL = 'mempool_is_full',
+    EPOCH_CHANGE_IN_PROGRESS = 'epoch_change_in_progress',
     INTERNAL_ERROR = 'internal_error',
     WEB_FRAMEWORK_ERROR = 'web_framework_error',
     BCS_NOT_SUPPORTED = 'bcs_not_supported',
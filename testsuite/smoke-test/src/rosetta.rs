@@ -9,7 +9,7 @@ use aptos::{account::create::DEFAULT_FUNDED_COINS, test::CliTestFramework};
 use aptos_config::config::PersistableConfig;
 use aptos_config::{config::ApiConfig, utils::get_available_port};
 use aptos_crypto::ed25519::Ed25519PrivateKey;
-use aptos_crypto::HashValue;
+use aptos_crypto::{HashValue, PrivateKey};
 use aptos_rest_client::aptos_api_types::UserTransaction;
 use aptos_rest_client::Transaction;
 use aptos_rosetta::types::{
@@ -17,8 +17,9 @@ use aptos_rosetta::types::{
     TransactionType,
 };
 use aptos_rosetta::{
-    client::RosettaClient,
+    client::{MultiKeySet, RosettaClient},
     common::{native_coin, BLOCKCHAIN, Y2K_MS},
+    error::ApiError,
     types::{
         AccountBalanceRequest, AccountBalanceResponse, BlockIdentifier, BlockRequest,
         NetworkIdentifier, NetworkRequest, PartialBlockIdentifier,
@@ -28,6 +29,7 @@ use aptos_rosetta::{
 use aptos_types::{account_address::AccountAddress, chain_id::ChainId};
 use forge::{LocalSwarm, Node, NodeExt};
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{future::Future, time::Duration};
@@ -901,6 +903,87 @@ async fn test_invalid_transaction_gas_charged() {
     );
 }
 
+#[tokio::test]
+async fn test_multi_ed25519_transfer() {
+    let (swarm, cli, _faucet, rosetta_client) = setup_test(1, 2).await;
+    let chain_id = swarm.chain_id();
+    let validator = swarm.validators().next().unwrap();
+    let rest_client = validator.rest_client();
+    let network_identifier = chain_id.into();
+
+    // A 2 of 3 account, where the first and last keys are available to sign
+    let private_key = |seed: u8| Ed25519PrivateKey::try_from([seed; 32].as_ref()).unwrap();
+    let public_keys: Vec<_> = (1..=3).map(|seed| private_key(seed).public_key()).collect();
+    let key_set =
+        MultiKeySet::new(public_keys.clone(), vec![private_key(1), private_key(3)], 2).unwrap();
+    let multi_account = key_set.account_address();
+
+    // Create and fund the account from a single key account
+    let private_key_0 = cli.private_key(0);
+    create_account_and_wait(
+        &rosetta_client,
+        &rest_client,
+        &network_identifier,
+        private_key_0,
+        multi_account,
+        Duration::from_secs(5),
+        None,
+    )
+    .await
+    .unwrap();
+    transfer_and_wait(
+        &rosetta_client,
+        &rest_client,
+        &network_identifier,
+        private_key_0,
+        multi_account,
+        1000000,
+        Duration::from_secs(5),
+        None,
+    )
+    .await
+    .unwrap();
+
+    // Transfer out of the account, with signatures from two of its keys
+    let receiver = cli.account_id(1);
+    let expiry_time = expiry_time(Duration::from_secs(5));
+    let txn_hash = rosetta_client
+        .transfer(
+            &network_identifier,
+            &key_set,
+            receiver,
+            20,
+            expiry_time.as_secs(),
+            None,
+        )
+        .await
+        .expect("Expect transfer to successfully submit to mempool")
+        .hash;
+    let txn = wait_for_transaction(&rest_client, expiry_time, txn_hash)
+        .await
+        .unwrap();
+    assert!(txn.info.success);
+    assert_eq!(multi_account, *txn.request.sender.inner());
+
+    // A single key can't meet the threshold
+    let key_set = MultiKeySet::new(public_keys, vec![private_key(2)], 2).unwrap();
+    let err = rosetta_client
+        .transfer(
+            &network_identifier,
+            &key_set,
+            receiver,
+            20,
+            expiry_time.as_secs(),
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ApiError>(),
+        Some(ApiError::UnsupportedSignatureCount(Some(1)))
+    ));
+}
+
 fn assert_transfer_transaction(
     sender: AccountAddress,
     receiver: AccountAddress,